@@ -0,0 +1,56 @@
+//! Compares the Intcode CPU against the same program compiled to Rust. Regenerate the compiled
+//! module with `cargo intcode compile benches/compiled/countdown.txt > benches/compiled/countdown.rs`.
+//! Run with `cargo bench --bench compiled`.
+use advent_of_code::intcode_computer::{
+    cpu::{Cpu, Engine},
+    program::Program,
+};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+#[rustfmt::skip]
+#[path = "compiled/countdown.rs"]
+mod countdown;
+
+const ITERATIONS: u32 = 20;
+/// The countdown loop runs this many times, then patches its `halt` to output 7.
+const COUNTER: i64 = 1_000_000;
+
+fn bench(mut run: impl FnMut() -> Vec<i64>) -> Duration {
+    let timer = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(black_box(run()), vec![7]);
+    }
+    timer.elapsed() / ITERATIONS
+}
+
+fn interpreted(program: &[i64], engine: Engine) -> Duration {
+    bench(|| {
+        let mut cpu = Cpu::new(program.to_vec());
+        cpu.set_engine(engine);
+        cpu.run(Some(vec![COUNTER])).unwrap();
+        cpu.outputs
+    })
+}
+
+fn main() {
+    let program = Program::load("benches/compiled/countdown.txt")
+        .expect("could not load program")
+        .into_vec();
+
+    println!("{:<12} {:>12}", "engine", "countdown");
+    for (name, engine) in [
+        ("interpreter", Engine::Interpreter),
+        ("predecoded", Engine::Predecoded),
+    ] {
+        println!("{name:<12} {:>12.2?}", interpreted(&program, engine));
+    }
+    let compiled = bench(|| {
+        let mut outputs = Vec::new();
+        countdown::run(|| COUNTER, |value| outputs.push(value)).unwrap();
+        outputs
+    });
+    println!("{:<12} {compiled:>12.2?}", "compiled");
+}
@@ -0,0 +1,48 @@
+//! Compiled from an Intcode program by `cargo intcode compile`, do not edit.
+#![allow(unused_assignments, unused_mut, unreachable_code, clippy::all)]
+use advent_of_code::intcode_computer::compile::runtime::*;
+
+const PROGRAM: &[i64] = &[
+    3, 24, 1001, 24, -1, 24, 1002, 24, 3, 25, 1007, 25,
+    0, 25, 1005, 24, 2, 1101, 0, 104, 21, 99, 7, 99,
+    0, 0,
+];
+
+pub fn run(
+    mut input: impl FnMut() -> i64,
+    mut output: impl FnMut(i64),
+) -> Result<(), CpuError> {
+    let mut mem: FlatMemory = PROGRAM.iter().copied().collect();
+    let mut rb: i64 = 0;
+    let mut pc: usize = 0;
+    loop {
+        pc = match pc {
+            0 => {
+                //     0: in [24]
+                let value = input();
+                write(&mut mem, 24, value, 0)?;
+                2
+            }
+            2 => {
+                //     2: add [24], #-1, [24]
+                let value = i64::checked_add(read(&mem, 24, 2)?, -1).ok_or(CpuError::Overflow { pc: 2 })?;
+                write(&mut mem, 24, value, 2)?;
+                //     6: mul [24], #3, [25]
+                let value = i64::checked_mul(read(&mem, 24, 6)?, 3).ok_or(CpuError::Overflow { pc: 6 })?;
+                write(&mut mem, 25, value, 6)?;
+                //    10: lt [25], #0, [25]
+                let value = (read(&mem, 25, 10)? < 0) as i64;
+                write(&mut mem, 25, value, 10)?;
+                //    14: jt [24], #2
+                if read(&mem, 24, 14)? != 0 { 2 } else { 17 }
+            }
+            17 => {
+                //    17: add #0, #104, [21]
+                let value = 104;
+                write(&mut mem, 21, value, 17)?;
+                return fallback(mem, 21, rb, input, output);
+            }
+            _ => return fallback(mem, pc, rb, input, output),
+        };
+    }
+}
@@ -0,0 +1,93 @@
+//! Compares the instruction fetching engines of the Intcode CPU on days 2, 5, 7 and 9.
+//!
+//! Uses the puzzle inputs in `data/inputs` where available and examples otherwise.
+//! Run with `cargo bench --bench engine`.
+use advent_of_code::intcode_computer::{
+    asm::assemble,
+    cpu::{Cpu, Engine},
+    program::Program,
+};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 20;
+
+fn load(day: &str) -> Option<Vec<i64>> {
+    let program = Program::load(format!("data/inputs/{day}.txt")).ok()?;
+    Some(program.into_vec())
+}
+
+fn example(name: &str) -> Vec<i64> {
+    Program::load(format!("data/examples/{name}.txt"))
+        .expect("could not load example")
+        .into_vec()
+}
+
+fn bench(program: &[i64], inputs: &[i64], engine: Engine) -> Duration {
+    let timer = Instant::now();
+    for _ in 0..ITERATIONS {
+        let mut cpu = Cpu::new(program.to_vec());
+        cpu.set_engine(engine);
+        // examples are not necessarily valid for the puzzle inputs, errors are fine here.
+        let _ = black_box(cpu.run(Some(inputs.to_vec())));
+        black_box(cpu.outputs);
+    }
+    timer.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let day_2 = match load("02") {
+        Some(mut program) => {
+            program[1] = 12;
+            program[2] = 2;
+            program
+        }
+        None => vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+    };
+
+    // long-running loop, shows the steady state once every instruction is cached.
+    let countdown = assemble(
+        "        in [counter]
+         loop:   add [counter], #-1, [counter]
+                 mul [counter], #3, [scratch]
+                 lt [scratch], #0, [scratch]
+                 jt [counter], #loop
+                 out [counter]
+                 halt
+         counter: data 0
+         scratch: data 0",
+    )
+    .unwrap();
+
+    let programs = [
+        ("day 2", day_2, vec![]),
+        (
+            "day 5",
+            load("05").unwrap_or_else(|| example("05-6")),
+            vec![5],
+        ),
+        (
+            "day 7",
+            load("07").unwrap_or_else(|| example("07-1")),
+            vec![4, 0],
+        ),
+        (
+            "day 9",
+            load("09").unwrap_or_else(|| example("09-1")),
+            vec![2],
+        ),
+        ("countdown", countdown, vec![100_000]),
+    ];
+
+    println!(
+        "{:<10} {:>12} {:>12}",
+        "program", "interpreter", "predecoded"
+    );
+    for (name, program, inputs) in programs {
+        let interpreter = bench(&program, &inputs, Engine::Interpreter);
+        let predecoded = bench(&program, &inputs, Engine::Predecoded);
+        println!("{name:<10} {interpreter:>12.2?} {predecoded:>12.2?}");
+    }
+}
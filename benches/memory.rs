@@ -0,0 +1,65 @@
+//! Compares the `Memory` backends of the Intcode CPU.
+//!
+//! Run with `cargo bench --bench memory`.
+use advent_of_code::intcode_computer::{
+    asm::assemble,
+    cpu::Cpu,
+    memory::{FlatMemory, Memory, SparseMemory},
+    program::Program,
+};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 200;
+
+fn read_example(name: &str) -> Vec<i64> {
+    Program::load(format!("data/examples/{name}.txt"))
+        .expect("could not load example file")
+        .into_vec()
+}
+
+fn bench<M: Memory>(program: &[i64], inputs: &[i64]) -> Duration {
+    let timer = Instant::now();
+    for _ in 0..ITERATIONS {
+        let mut cpu: Cpu<M> = Cpu::with_memory(program.to_vec());
+        cpu.run(Some(inputs.to_vec())).unwrap();
+        black_box(cpu.outputs);
+    }
+    timer.elapsed() / ITERATIONS
+}
+
+fn main() {
+    // counts down from the input, storing every value in a table at a high address.
+    let countdown = assemble(
+        "        in [counter]
+         loop:   add [counter], #-1, [counter]
+                 add [counter], #table, [ptr]
+                 rb [ptr]
+                 add [counter], #0, rb+0
+                 mul [ptr], #-1, [ptr]
+                 rb [ptr]
+                 jt [counter], #loop
+                 out [counter]
+                 halt
+         counter: data 0
+         ptr:     data 0
+         table:   data 0",
+    )
+    .unwrap();
+
+    let programs = [
+        ("quine (09-1)", read_example("09-1"), vec![]),
+        ("compare (05-6)", read_example("05-6"), vec![8]),
+        ("amplifier (07-1)", read_example("07-1"), vec![4, 0]),
+        ("countdown", countdown, vec![10_000]),
+    ];
+
+    println!("{:<18} {:>12} {:>12}", "program", "flat", "sparse");
+    for (name, program, inputs) in programs {
+        let flat = bench::<FlatMemory>(&program, &inputs);
+        let sparse = bench::<SparseMemory>(&program, &inputs);
+        println!("{name:<18} {flat:>12.2?} {sparse:>12.2?}");
+    }
+}
@@ -1,4 +1,5 @@
 use advent_of_code::intcode_computer::cpu::Cpu;
+use advent_of_code::intcode_computer::program::Program;
 
 advent_of_code::solution!(5);
 
@@ -11,9 +12,14 @@ pub fn part_two(input: &str) -> Option<u32> {
 }
 
 fn run(program: &str, input: i64) -> Option<u32> {
-    let program: Vec<_> = program.split(',').map(|s| s.parse().unwrap()).collect();
+    let program = Program::parse(program)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .into_vec();
     let mut cpu = Cpu::new(program);
-    cpu.run(Some(vec![input]));
+    cpu.run(Some(vec![input]))
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
     cpu.outputs.last().map(|&output| output as u32)
 }
 
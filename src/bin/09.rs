@@ -1,58 +1,65 @@
 use advent_of_code::intcode_computer::cpu::Cpu;
+use advent_of_code::intcode_computer::program::Program;
 
 advent_of_code::solution!(9);
 
 pub fn part_one(input: &str) -> Option<i64> {
-    let program: Vec<_> = input.split(',').map(|s| s.parse().unwrap()).collect();
+    let program = Program::parse(input)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .into_vec();
     let mut cpu = Cpu::new(program);
-    cpu.run(Some(vec![1]));
-    Some(*cpu.outputs.last().unwrap())
+    cpu.run(Some(vec![1])).map_err(|e| eprintln!("{e}")).ok()?;
+    cpu.outputs.last().copied()
 }
 
 pub fn part_two(input: &str) -> Option<i64> {
-    let program: Vec<_> = input.split(',').map(|s| s.parse().unwrap()).collect();
+    let program = Program::parse(input)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .into_vec();
     let mut cpu = Cpu::new(program);
-    cpu.run(Some(vec![2]));
-    Some(*cpu.outputs.last().unwrap())
+    cpu.run(Some(vec![2])).map_err(|e| eprintln!("{e}")).ok()?;
+    cpu.outputs.last().copied()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use itertools::Itertools;
+    use advent_of_code::intcode_computer::asm::assemble;
 
     #[test]
     fn test_quine() {
         let input = advent_of_code::template::read_file_part("examples", DAY, 1);
-        let program: Vec<_> = input.split(',').map(|s| s.parse().unwrap()).collect();
+        let program = Program::parse(&input).unwrap().into_vec();
         let mut cpu = Cpu::new(program.clone());
-        cpu.run(None);
-        let memory_after_run: Vec<_> = cpu
-            .program
-            .into_iter()
-            .sorted_by_key(|memory| memory.0)
-            .map(|(_, v)| v)
-            .take(program.len())
-            .collect();
+        cpu.run(None).unwrap();
+        let memory_after_run: Vec<_> = (0..program.len()).map(|addr| cpu.peek(addr)).collect();
         assert_eq!(memory_after_run, program);
     }
 
     #[test]
     fn test_large() {
         let input = advent_of_code::template::read_file_part("examples", DAY, 2);
-        let program: Vec<_> = input.split(',').map(|s| s.parse().unwrap()).collect();
+        let program = Program::parse(&input).unwrap().into_vec();
         let mut cpu = Cpu::new(program.clone());
-        cpu.run(None);
+        cpu.run(None).unwrap();
         assert_eq!(cpu.outputs.last().unwrap(), &program[1]);
     }
 
     #[test]
     fn test_relative() {
-        let program = vec![109, 19, 204, -34, 99];
+        let program = assemble(
+            "rb #19
+             out rb-34
+             halt",
+        )
+        .unwrap();
+        assert_eq!(program, vec![109, 19, 204, -34, 99]);
         let mut cpu = Cpu::new(program);
         cpu.relative_base = 2000;
-        cpu.program.insert(1985, 42);
-        cpu.run(None);
+        cpu.poke(1985, 42);
+        cpu.run(None).unwrap();
         assert_eq!(cpu.outputs.last().unwrap(), &42);
     }
 }
@@ -1,8 +1,9 @@
-use advent_of_code::intcode_computer::cpu::{Cpu, Msg};
+use advent_of_code::intcode_computer::cpu::Cpu;
+use advent_of_code::intcode_computer::io::{DeviceError, IntcodeInput, IntcodeOutput};
+use advent_of_code::intcode_computer::program::Program;
 use itertools::Itertools;
 use std::cmp::Ordering::*;
 use std::collections::HashMap;
-use tokio::sync::mpsc;
 
 advent_of_code::solution!(13);
 
@@ -13,34 +14,36 @@ enum Tile {
     Ball,
 }
 
-impl From<i64> for Tile {
-    fn from(value: i64) -> Self {
+impl TryFrom<i64> for Tile {
+    type Error = DeviceError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
         match value {
-            0 | 1 => Self::Other,
-            2 => Self::Block,
-            3 => Self::Paddle,
-            4 => Self::Ball,
-            _ => unreachable!(),
+            0 | 1 => Ok(Self::Other),
+            2 => Ok(Self::Block),
+            3 => Ok(Self::Paddle),
+            4 => Ok(Self::Ball),
+            _ => Err(DeviceError::Invalid(format!("tile {value}"))),
         }
     }
 }
 
 pub fn part_one(input: &str) -> Option<u32> {
-    let program: Vec<_> = input
-        .trim()
-        .split(',')
-        .map(|s| s.parse().unwrap())
-        .collect();
+    let program = Program::parse(input)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .into_vec();
     let mut cpu = Cpu::new(program);
-    cpu.run(None);
+    cpu.run(None).map_err(|e| eprintln!("{e}")).ok()?;
 
-    let mut grid: HashMap<(i64, i64), Tile> = HashMap::new();
-    cpu.outputs
+    let grid: HashMap<(i64, i64), Tile> = cpu
+        .outputs
         .iter()
-        .tuples::<(&i64, &i64, &i64)>()
-        .for_each(|(&x, &y, &t)| {
-            grid.insert((x, y), Tile::from(t));
-        });
+        .tuples()
+        .map(|(&x, &y, &t)| Ok(((x, y), Tile::try_from(t)?)))
+        .collect::<Result<_, DeviceError>>()
+        .map_err(|e| eprintln!("{e:?}"))
+        .ok()?;
     Some(
         grid.into_values()
             .filter(|t| matches!(t, Tile::Block))
@@ -48,62 +51,98 @@ pub fn part_one(input: &str) -> Option<u32> {
     )
 }
 
+#[derive(Default)]
+struct Arcade {
+    ball: Option<i64>,
+    paddle: Option<i64>,
+    score: i64,
+    frame: Vec<i64>,
+}
+
+impl IntcodeInput for Arcade {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        let joystick_input = match self.ball.cmp(&self.paddle) {
+            Less => -1,
+            Equal => 0,
+            Greater => 1,
+        };
+        Ok(joystick_input)
+    }
+}
+
+impl IntcodeOutput for Arcade {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        self.frame.push(value);
+        if let [x, y, value] = self.frame[..] {
+            match (x, y) {
+                (-1, 0) => self.score = value,
+                _ => match Tile::try_from(value)? {
+                    Tile::Paddle => self.paddle = Some(x),
+                    Tile::Ball => self.ball = Some(x),
+                    _ => (),
+                },
+            }
+            self.frame.clear();
+        }
+        Ok(())
+    }
+}
+
 pub fn part_two(input: &str) -> Option<u32> {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async {
-        let mut program: Vec<_> = input
-            .trim()
-            .split(',')
-            .map(|s| s.parse().unwrap())
-            .collect();
-        *program.get_mut(0).unwrap() = 2;
+    let mut program = Program::parse(input)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .into_vec();
+    *program.get_mut(0).unwrap() = 2;
 
-        let (mut cpu, cpu_input) = Cpu::new_async(program);
+    let mut arcade = Arcade::default();
+    Cpu::new(program)
+        .run_device(&mut arcade)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
+    Some(arcade.score as u32)
+}
 
-        let (tx, mut rx) = mpsc::channel(32);
-        tokio::spawn(async move {
-            cpu.run_async(tx).await;
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code::intcode_computer::{asm::assemble, error::CpuError};
 
-        let mut ball = None;
-        let mut paddle = None;
-        let mut score = 0;
+    #[test]
+    fn test_arcade() {
+        // draws a ball right of the paddle, then reports the joystick input as score.
+        let program = assemble(
+            "    out #5
+                 out #1
+                 out #4
+                 out #3
+                 out #1
+                 out #3
+                 in [joystick]
+                 mul [joystick], #100, [joystick]
+                 out #-1
+                 out #0
+                 out [joystick]
+                 halt
+             joystick: data 0",
+        )
+        .unwrap();
 
-        loop {
-            match rx.recv().await {
-                Some(Msg::Value(-1)) => {
-                    assert_eq!(rx.recv().await.unwrap(), Msg::Value(0));
-                    if let Msg::Value(s) = rx.recv().await.unwrap() {
-                        score = s;
-                    } else {
-                        unreachable!()
-                    }
-                }
-                Some(Msg::Value(x)) => {
-                    let y = rx.recv().await.unwrap();
-                    let tile = rx.recv().await.unwrap();
-                    if let (Msg::Value(_), Msg::Value(tile)) = (y, tile) {
-                        match Tile::from(tile) {
-                            Tile::Paddle => paddle = Some(x),
-                            Tile::Ball => ball = Some(x),
-                            _ => (),
-                        }
-                    } else {
-                        unreachable!();
-                    }
-                }
-                Some(Msg::RxRequest) => {
-                    let joystick_input = match ball.cmp(&paddle) {
-                        Less => -1,
-                        Equal => 0,
-                        Greater => 1,
-                    };
-                    cpu_input.send(Msg::Value(joystick_input)).await.unwrap();
-                }
-                None => break,
-            }
-        }
+        let mut arcade = Arcade::default();
+        Cpu::new(program).run_device(&mut arcade).unwrap();
+        assert_eq!((arcade.ball, arcade.paddle), (Some(5), Some(3)));
+        assert_eq!(arcade.score, 100);
+    }
 
-        Some(score as u32)
-    })
+    #[test]
+    fn test_invalid_tile() {
+        let program = assemble("out #1\nout #2\nout #7\nhalt").unwrap();
+        assert_eq!(
+            Cpu::new(program).run_device(&mut Arcade::default()),
+            Err(CpuError::InvalidInput {
+                pc: 4,
+                input: "tile 7".to_string()
+            })
+        );
+    }
 }
@@ -0,0 +1,329 @@
+use super::disasm::{Instruction, Param};
+use super::opcode::OpCode;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Where control can go after an instruction.
+struct Exits {
+    targets: Vec<usize>,
+    falls_through: bool,
+    indirect: bool,
+}
+
+impl Exits {
+    fn of(instruction: &Instruction) -> Self {
+        let mut exits = Exits {
+            targets: Vec::new(),
+            falls_through: false,
+            indirect: false,
+        };
+        let Instruction::Op { opcode, params, .. } = instruction else {
+            return exits;
+        };
+
+        match opcode {
+            OpCode::Halt => (),
+            OpCode::Jt | OpCode::Jf => {
+                let (taken, not_taken) = match params[0] {
+                    Param::Immediate(c) => {
+                        let taken = (c != 0) == (*opcode == OpCode::Jt);
+                        (taken, !taken)
+                    }
+                    _ => (true, true),
+                };
+                exits.falls_through = not_taken;
+                if taken {
+                    match params[1] {
+                        Param::Immediate(target) if target >= 0 => {
+                            exits.targets.push(target as usize)
+                        }
+                        _ => exits.indirect = true,
+                    }
+                }
+            }
+            _ => exits.falls_through = true,
+        }
+        exits
+    }
+}
+
+/// The value of `add #x, #y, rb+n` or `mul #x, #y, rb+n`, i.e. a constant pushed to the stack.
+pub(super) fn pushed_constant(instruction: &Instruction) -> Option<i64> {
+    let Instruction::Op { opcode, params, .. } = instruction else {
+        return None;
+    };
+    match (opcode, &params[..]) {
+        (OpCode::Add, [Param::Immediate(x), Param::Immediate(y), Param::Relative(_)]) => {
+            x.checked_add(*y)
+        }
+        (OpCode::Mul, [Param::Immediate(x), Param::Immediate(y), Param::Relative(_)]) => {
+            x.checked_mul(*y)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub successors: Vec<usize>,
+    /// Ends in a jump whose target is only known at runtime.
+    pub indirect: bool,
+    /// Ends in a call of the function at this address, which returns to [`Block::end`].
+    pub call: Option<usize>,
+}
+
+impl Block {
+    pub fn end(&self) -> usize {
+        self.instructions
+            .last()
+            .map_or(self.start, |last| last.addr() + last.size())
+    }
+
+    /// A block that pushes its own end address to the stack and then jumps unconditionally is
+    /// a call.
+    fn call_target(&self) -> Option<usize> {
+        let exits = Exits::of(self.instructions.last()?);
+        let [target] = exits.targets[..] else {
+            return None;
+        };
+        let pushes_return = self
+            .instructions
+            .iter()
+            .any(|instruction| pushed_constant(instruction) == Some(self.end() as i64));
+        (!exits.falls_through && pushes_return).then_some(target)
+    }
+}
+
+/// A write by the instruction at `pc` into a word decoded as reachable code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfModification {
+    pub pc: usize,
+    pub target: usize,
+}
+
+/// The control-flow graph of the code reachable from address 0, found by following every
+/// fallthrough and immediate jump target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>,
+    pub self_modifications: Vec<SelfModification>,
+}
+
+impl Cfg {
+    /// Code after a call is explored as well, see [`Block::call`].
+    pub fn build(program: &[i64]) -> Self {
+        let mut roots = vec![0];
+        let (instructions, mut blocks) = loop {
+            let (instructions, leaders) = Self::explore(program, &roots);
+            let blocks = Self::split(&instructions, &leaders);
+            let returns: Vec<_> = blocks
+                .values()
+                .filter(|block| block.call_target().is_some())
+                .map(|block| block.end())
+                .filter(|addr| *addr < program.len() && !instructions.contains_key(addr))
+                .collect();
+            if returns.is_empty() {
+                break (instructions, blocks);
+            }
+            roots.extend(returns);
+        };
+
+        for block in blocks.values_mut() {
+            let exits = Exits::of(block.instructions.last().unwrap());
+            block.call = block.call_target();
+            block.successors = exits.targets;
+            if (exits.falls_through || block.call.is_some())
+                && instructions.contains_key(&block.end())
+            {
+                block.successors.push(block.end());
+            }
+            block.successors.retain(|target| *target < program.len());
+            block.successors.dedup();
+            block.indirect = exits.indirect;
+        }
+
+        let code: BTreeSet<usize> = instructions
+            .values()
+            .flat_map(|instruction| instruction.addr()..instruction.addr() + instruction.size())
+            .collect();
+        let self_modifications = instructions
+            .values()
+            .filter_map(|instruction| match instruction {
+                Instruction::Op {
+                    addr,
+                    opcode,
+                    params,
+                } if opcode.writes() => match params.last() {
+                    Some(Param::Position(target))
+                        if *target >= 0 && code.contains(&(*target as usize)) =>
+                    {
+                        Some(SelfModification {
+                            pc: *addr,
+                            target: *target as usize,
+                        })
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            blocks,
+            self_modifications,
+        }
+    }
+
+    /// Decodes every instruction reachable from `roots`, returning them with the addresses that
+    /// start a basic block.
+    fn explore(
+        program: &[i64],
+        roots: &[usize],
+    ) -> (BTreeMap<usize, Instruction>, BTreeSet<usize>) {
+        let mut instructions: BTreeMap<usize, Instruction> = BTreeMap::new();
+        let mut leaders: BTreeSet<usize> = roots.iter().copied().collect();
+        let mut queue = roots.to_vec();
+
+        while let Some(addr) = queue.pop() {
+            if addr >= program.len() || instructions.contains_key(&addr) {
+                continue;
+            }
+            let instruction = Instruction::decode(program, addr);
+            let exits = Exits::of(&instruction);
+            let next = addr + instruction.size();
+            if exits.falls_through {
+                queue.push(next);
+            }
+            if !exits.targets.is_empty() || exits.indirect {
+                leaders.insert(next);
+            }
+            for &target in &exits.targets {
+                leaders.insert(target);
+                queue.push(target);
+            }
+            instructions.insert(addr, instruction);
+        }
+        (instructions, leaders)
+    }
+
+    fn split(
+        instructions: &BTreeMap<usize, Instruction>,
+        leaders: &BTreeSet<usize>,
+    ) -> BTreeMap<usize, Block> {
+        let mut blocks: BTreeMap<usize, Block> = BTreeMap::new();
+        let mut current: Option<Block> = None;
+        for instruction in instructions.values() {
+            let addr = instruction.addr();
+            let continues = current.as_ref().is_some_and(|block| {
+                !leaders.contains(&addr)
+                    && block.end() == addr
+                    && block
+                        .instructions
+                        .last()
+                        .is_some_and(|last| Exits::of(last).falls_through)
+            });
+            if !continues {
+                if let Some(block) = current.take() {
+                    blocks.insert(block.start, block);
+                }
+                current = Some(Block {
+                    start: addr,
+                    instructions: Vec::new(),
+                    successors: Vec::new(),
+                    indirect: false,
+                    call: None,
+                });
+            }
+            current
+                .as_mut()
+                .unwrap()
+                .instructions
+                .push(instruction.clone());
+        }
+        blocks.extend(current.map(|block| (block.start, block)));
+        blocks
+    }
+
+    /// Addresses of every word that belongs to a reachable instruction.
+    pub fn code(&self) -> BTreeSet<usize> {
+        self.blocks
+            .values()
+            .flat_map(|block| &block.instructions)
+            .flat_map(|instruction| instruction.addr()..instruction.addr() + instruction.size())
+            .collect()
+    }
+
+    /// Renders the graph in Graphviz DOT format. Blocks ending in an indirect jump are drawn in
+    /// red, blocks that are overwritten at runtime are filled.
+    pub fn to_dot(&self) -> String {
+        let targets: BTreeSet<usize> = self.self_modifications.iter().map(|m| m.target).collect();
+
+        let mut dot =
+            String::from("digraph intcode {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let label: String = block
+                .instructions
+                .iter()
+                .map(|instruction| format!("{:>5}: {instruction}\\l", instruction.addr()))
+                .collect();
+            let mut attributes = format!("label=\"{label}\"");
+            if block.indirect {
+                attributes.push_str(", color=red");
+            }
+            if targets.range(block.start..block.end()).next().is_some() {
+                attributes.push_str(", style=filled, fillcolor=lightyellow");
+            }
+            writeln!(dot, "    b{} [{attributes}];", block.start).unwrap();
+        }
+        for block in self.blocks.values() {
+            for successor in &block.successors {
+                writeln!(dot, "    b{} -> b{successor};", block.start).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+
+    #[test]
+    fn test_cfg() {
+        let program = assemble(
+            "        in [counter]
+             loop:   add [counter], #-1, [counter]
+                     add #0, #99, [patch]
+                     jt [counter], #loop
+                     jf [counter], rb+0
+             patch:  halt
+             counter: data 0",
+        )
+        .unwrap();
+
+        let cfg = Cfg::build(&program);
+        let starts: Vec<_> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, vec![0, 2, 13, 16]);
+        assert_eq!(cfg.blocks[&0].successors, vec![2]);
+        assert_eq!(cfg.blocks[&2].successors, vec![2, 13]);
+        assert!(cfg.blocks[&13].indirect);
+        assert_eq!(cfg.blocks[&13].successors, vec![16]);
+        assert_eq!(
+            cfg.self_modifications,
+            vec![SelfModification { pc: 6, target: 16 }]
+        );
+        assert!(cfg.code().contains(&16) && !cfg.code().contains(&17));
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph intcode {"));
+        assert!(dot.contains("b2 -> b2;") && dot.contains("b2 -> b13;"));
+        assert!(dot.contains("b13 [label=\"   13: jf [17], rb+0\\l\", color=red];"));
+        assert!(
+            dot.contains("b16 [label=\"   16: halt\\l\", style=filled, fillcolor=lightyellow];")
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use super::cpu::{Cpu, Event};
+use super::error::CpuError;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsciiResult {
+    pub text: String,
+    /// Outputs outside of the ASCII range, e.g. a final puzzle answer.
+    pub non_ascii_values: Vec<i64>,
+}
+
+impl AsciiResult {
+    fn push(&mut self, value: i64) {
+        match u8::try_from(value) {
+            Ok(byte) if byte.is_ascii() => self.text.push(byte as char),
+            _ => self.non_ascii_values.push(value),
+        }
+    }
+}
+
+/// Wraps a [`Cpu`] that reads and writes ASCII text.
+pub struct AsciiCpu {
+    pub cpu: Cpu,
+    halted: bool,
+}
+
+impl AsciiCpu {
+    pub fn new(program: Vec<i64>) -> Self {
+        Self::from(Cpu::new(program))
+    }
+
+    /// Queues `line` as input, terminated with a newline.
+    pub fn send_line(&mut self, line: &str) {
+        for byte in line.bytes().chain(std::iter::once(b'\n')) {
+            self.cpu.provide_input(byte as i64);
+        }
+    }
+
+    /// Runs until the program halts or waits for more input, returning the output produced
+    /// since the last call.
+    pub fn run(&mut self) -> Result<AsciiResult, CpuError> {
+        let mut result = AsciiResult::default();
+        loop {
+            match self.cpu.resume()? {
+                Event::Output(value) => result.push(value),
+                Event::NeedsInput => return Ok(result),
+                Event::Halted => {
+                    self.halted = true;
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
+
+impl From<Cpu> for AsciiCpu {
+    fn from(cpu: Cpu) -> Self {
+        Self { cpu, halted: false }
+    }
+}
+
+/// Runs `program` to completion, feeding it every line of `script`.
+pub fn run_ascii(program: Vec<i64>, script: &str) -> Result<AsciiResult, CpuError> {
+    let mut cpu = AsciiCpu::new(program);
+    for line in script.lines() {
+        cpu.send_line(line);
+    }
+
+    let result = cpu.run()?;
+    if cpu.is_halted() {
+        Ok(result)
+    } else {
+        Err(CpuError::InputExhausted { pc: cpu.cpu.pc() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+
+    #[test]
+    fn test_ascii() {
+        let program = assemble(
+            "loop: in [c]
+                   out [c]
+                   eq [c], #10, [t]
+                   jf [t], #loop
+                   out #1000
+                   halt
+             c:    data 0
+             t:    data 0",
+        )
+        .unwrap();
+
+        let result = run_ascii(program.clone(), "hi").unwrap();
+        assert_eq!(result.text, "hi\n");
+        assert_eq!(result.non_ascii_values, vec![1000]);
+        assert_eq!(
+            run_ascii(program, ""),
+            Err(CpuError::InputExhausted { pc: 0 })
+        );
+    }
+}
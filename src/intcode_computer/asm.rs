@@ -0,0 +1,277 @@
+use super::opcode::OpCode;
+use std::{collections::HashMap, fmt::Display};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    OperandCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    InvalidLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {line}: unknown mnemonic {mnemonic:?}")
+            }
+            AsmError::OperandCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: expected {expected} operands, found {found}"
+            ),
+            AsmError::InvalidOperand { line, operand } => {
+                write!(f, "line {line}: invalid operand {operand:?}")
+            }
+            AsmError::InvalidLabel { line, label } => {
+                write!(f, "line {line}: invalid label {label:?}")
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "line {line}: label {label:?} is already defined")
+            }
+            AsmError::UndefinedLabel { line, label } => {
+                write!(f, "line {line}: label {label:?} is not defined")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// A value that is either a literal or a label reference with an optional offset.
+enum Expr {
+    Value(i64),
+    Label(String, i64),
+}
+
+struct Line {
+    line: usize,
+    words: Vec<Expr>,
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "rb"
+        && s != "data"
+        && OpCode::from_mnemonic(s).is_none()
+}
+
+fn parse_expr(s: &str, line: usize) -> Result<Expr, AsmError> {
+    let s = s.trim();
+    if let Ok(value) = s.parse() {
+        return Ok(Expr::Value(value));
+    }
+
+    let (label, offset) = match s.find(['+', '-']) {
+        Some(i) => {
+            let offset = s[i..].replace(' ', "");
+            let offset = offset
+                .strip_prefix('+')
+                .unwrap_or(&offset)
+                .parse()
+                .map_err(|_| AsmError::InvalidOperand {
+                    line,
+                    operand: s.to_string(),
+                })?;
+            (s[..i].trim(), offset)
+        }
+        None => (s, 0),
+    };
+
+    if is_identifier(label) {
+        Ok(Expr::Label(label.to_string(), offset))
+    } else {
+        Err(AsmError::InvalidOperand {
+            line,
+            operand: s.to_string(),
+        })
+    }
+}
+
+/// Parses an operand into its parameter mode and value.
+fn parse_operand(s: &str, line: usize) -> Result<(i64, Expr), AsmError> {
+    let invalid = || AsmError::InvalidOperand {
+        line,
+        operand: s.to_string(),
+    };
+
+    if let Some(imm) = s.strip_prefix('#') {
+        Ok((1, parse_expr(imm, line)?))
+    } else if let Some(pos) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Ok((0, parse_expr(pos, line)?))
+    } else if let Some(rel) = s.strip_prefix("rb") {
+        let rel = rel.replace(' ', "");
+        let offset = match rel.strip_prefix('+') {
+            _ if rel.is_empty() => 0,
+            Some(offset) => offset.parse().map_err(|_| invalid())?,
+            None => rel.parse().map_err(|_| invalid())?,
+        };
+        Ok((2, Expr::Value(offset)))
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parse_statement(statement: &str, line: usize) -> Result<Vec<Expr>, AsmError> {
+    let (head, rest) = statement
+        .split_once(char::is_whitespace)
+        .unwrap_or((statement, ""));
+    let operands: Vec<_> = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(str::trim).collect()
+    };
+
+    if head == "data" {
+        return operands
+            .into_iter()
+            .map(|operand| parse_expr(operand, line))
+            .collect();
+    }
+
+    let opcode = OpCode::from_mnemonic(head).ok_or_else(|| AsmError::UnknownMnemonic {
+        line,
+        mnemonic: head.to_string(),
+    })?;
+    if operands.len() != opcode.params() {
+        return Err(AsmError::OperandCount {
+            line,
+            expected: opcode.params(),
+            found: operands.len(),
+        });
+    }
+
+    let operands = operands
+        .into_iter()
+        .map(|operand| parse_operand(operand, line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let instruction = operands
+        .iter()
+        .enumerate()
+        .fold(opcode.code(), |instruction, (i, (mode, _))| {
+            instruction + mode * 10_i64.pow(2 + i as u32)
+        });
+
+    Ok(std::iter::once(Expr::Value(instruction))
+        .chain(operands.into_iter().map(|(_, expr)| expr))
+        .collect())
+}
+
+/// Assembles a textual Intcode program.
+///
+/// Each line holds an optional `label:`, followed by either an instruction using the mnemonics of
+/// [`OpCode`] or a `data` directive with a comma separated list of values. Operands are written
+/// as `#imm` (immediate), `[addr]` (position) or `rb+n` (relative); immediate and position
+/// operands as well as data values may refer to labels, optionally with an offset like `[buf+2]`.
+/// Everything after a `;` is a comment.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut lines: Vec<Line> = Vec::new();
+    let mut addr = 0;
+
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut text = text.split(';').next().unwrap_or_default().trim();
+
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(AsmError::InvalidLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            if labels.insert(label.to_string(), addr).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            text = rest.trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let words = parse_statement(text, line)?;
+        addr += words.len();
+        lines.push(Line { line, words });
+    }
+
+    let mut program = Vec::with_capacity(addr);
+    for Line { line, words } in lines {
+        for word in words {
+            let value = match word {
+                Expr::Value(value) => value,
+                Expr::Label(label, offset) => match labels.get(&label) {
+                    Some(&addr) => addr as i64 + offset,
+                    None => return Err(AsmError::UndefinedLabel { line, label }),
+                },
+            };
+            program.push(value);
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{cpu::Cpu, disasm, program::Program};
+
+    #[test]
+    fn test_assemble_labels() {
+        let program = assemble(
+            "        in [counter]
+             loop:   out [counter]
+                     add [counter], #-1, [counter]
+                     jt [counter], #loop
+                     halt
+             counter: data 0",
+        )
+        .unwrap();
+        let mut cpu = Cpu::new(program);
+        cpu.run(Some(vec![3])).unwrap();
+        assert_eq!(cpu.outputs, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_disassemble_roundtrip() {
+        for part in 1..=2 {
+            let program = Program::load(format!("data/examples/09-{part}.txt"))
+                .unwrap()
+                .into_vec();
+            let source = disasm::source(&program);
+            assert_eq!(assemble(&source).unwrap(), program);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! An Intcode machine with arbitrary-precision words, enabled by the `bigint` feature.
+//!
+//! [`Cpu`](super::cpu::Cpu) works on `i64` and reports arithmetic that leaves its range as
+//! [`CpuError::Overflow`]. [`BigCpu`] runs the same programs on [`BigInt`] words instead, so
+//! values can grow without bound. Only plain input and output are supported, the debugging,
+//! tracing and profiling features of `Cpu` are not.
+use super::cpu::DEFAULT_MAX_STEPS;
+use super::error::CpuError;
+use super::opcode::OpCode::{self, *};
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use std::collections::{HashMap, VecDeque};
+
+pub struct BigCpu {
+    memory: HashMap<usize, BigInt>,
+    pc: usize,
+    pub relative_base: BigInt,
+    pub outputs: Vec<BigInt>,
+    pub max_steps: Option<u64>,
+    steps: u64,
+}
+
+impl BigCpu {
+    pub fn new(program: impl IntoIterator<Item = impl Into<BigInt>>) -> Self {
+        Self {
+            memory: program.into_iter().map(Into::into).enumerate().collect(),
+            pc: 0,
+            relative_base: BigInt::zero(),
+            outputs: Vec::new(),
+            max_steps: Some(DEFAULT_MAX_STEPS),
+            steps: 0,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn peek(&self, addr: usize) -> BigInt {
+        self.memory.get(&addr).cloned().unwrap_or_default()
+    }
+
+    pub fn poke(&mut self, addr: usize, value: BigInt) {
+        self.memory.insert(addr, value);
+    }
+
+    /// Converts a computed address, reporting values beyond `usize` as overflow.
+    fn to_addr(&self, addr: BigInt) -> Result<usize, CpuError> {
+        addr.to_usize().ok_or_else(|| match addr.to_i64() {
+            Some(addr) if addr < 0 => CpuError::NegativeAddress { pc: self.pc, addr },
+            _ => CpuError::Overflow { pc: self.pc },
+        })
+    }
+
+    fn addr(&self, (mode, value): &(u8, BigInt)) -> Result<usize, CpuError> {
+        match mode {
+            0 => self.to_addr(value.clone()),
+            2 => self.to_addr(&self.relative_base + value),
+            _ => Err(CpuError::WriteToImmediate { pc: self.pc }),
+        }
+    }
+
+    fn get(&self, param: &(u8, BigInt)) -> Result<BigInt, CpuError> {
+        match param {
+            (1, value) => Ok(value.clone()),
+            _ => Ok(self.peek(self.addr(param)?)),
+        }
+    }
+
+    /// Runs until the program halts, reading inputs from `inputs`. Outputs are collected in
+    /// [`BigCpu::outputs`].
+    pub fn run(&mut self, inputs: Vec<BigInt>) -> Result<(), CpuError> {
+        let mut inputs = VecDeque::from(inputs);
+        loop {
+            if self
+                .max_steps
+                .is_some_and(|max_steps| self.steps >= max_steps)
+            {
+                return Err(CpuError::StepLimit {
+                    pc: self.pc,
+                    steps: self.steps,
+                });
+            }
+            self.steps += 1;
+
+            let pc = self.pc;
+            // digits above the modes are ignored, as in `Cpu`.
+            let code = (self.peek(pc) % 100_000i64)
+                .to_i64()
+                .expect("remainder fits");
+            let modes = [code / 100 % 10, code / 1_000 % 10, code / 10_000 % 10].map(|m| m as u8);
+            if let Some(&mode) = modes.iter().find(|&&mode| mode > 2) {
+                return Err(CpuError::InvalidMode { pc, mode });
+            }
+            let opcode =
+                OpCode::try_from(code).map_err(|code| CpuError::InvalidOpCode { pc, code })?;
+            let params: Vec<_> = (0..opcode.params())
+                .map(|i| (modes[i], self.peek(pc + 1 + i)))
+                .collect();
+            let mut next = pc + 1 + opcode.params();
+
+            match opcode {
+                Add => self.set(&params[2], self.get(&params[0])? + self.get(&params[1])?)?,
+                Mul => self.set(&params[2], self.get(&params[0])? * self.get(&params[1])?)?,
+                In => {
+                    let value = inputs.pop_front().ok_or(CpuError::InputExhausted { pc })?;
+                    self.set(&params[0], value)?
+                }
+                Out => {
+                    let value = self.get(&params[0])?;
+                    self.outputs.push(value);
+                }
+                Jt | Jf => {
+                    if self.get(&params[0])?.is_zero() == (opcode == Jf) {
+                        next = self.to_addr(self.get(&params[1])?)?;
+                    }
+                }
+                Lt => {
+                    let value = self.get(&params[0])? < self.get(&params[1])?;
+                    self.set(&params[2], BigInt::from(value as u8))?
+                }
+                Eq => {
+                    let value = self.get(&params[0])? == self.get(&params[1])?;
+                    self.set(&params[2], BigInt::from(value as u8))?
+                }
+                Rb => self.relative_base += self.get(&params[0])?,
+                Halt => return Ok(()),
+            }
+            self.pc = next;
+        }
+    }
+
+    fn set(&mut self, param: &(u8, BigInt), value: BigInt) -> Result<(), CpuError> {
+        let addr = self.addr(param)?;
+        self.memory.insert(addr, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{asm::assemble, cpu::Cpu, program::Program};
+
+    #[test]
+    fn test_matches_cpu() {
+        for (example, input) in [("05-6", 7), ("05-6", 9), ("09-1", 0), ("09-2", 0)] {
+            let program = Program::load(format!("data/examples/{example}.txt"))
+                .unwrap()
+                .into_vec();
+            let mut cpu = Cpu::new(program.clone());
+            cpu.run(Some(vec![input])).unwrap();
+            let mut big = BigCpu::new(program);
+            big.run(vec![input.into()]).unwrap();
+            assert_eq!(big.steps(), cpu.steps());
+            let outputs: Vec<BigInt> = cpu.outputs.into_iter().map(Into::into).collect();
+            assert_eq!(big.outputs, outputs);
+        }
+    }
+
+    #[test]
+    fn test_large_values() {
+        let program = assemble(
+            "        in [x]
+             loop:   mul [x], [x], [x]
+                     add [n], #-1, [n]
+                     jt [n], #loop
+                     out [x]
+                     halt
+             x:      data 0
+             n:      data 7",
+        )
+        .unwrap();
+        let mut cpu = Cpu::new(program.clone());
+        assert_eq!(cpu.run(Some(vec![3])), Err(CpuError::Overflow { pc: 2 }));
+
+        let mut big = BigCpu::new(program);
+        big.run(vec![3.into()]).unwrap();
+        assert_eq!(big.outputs, vec![BigInt::from(3).pow(128)]);
+        assert_eq!(big.pc(), 15);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut big = BigCpu::new([204, -1, 99]);
+        assert_eq!(
+            big.run(vec![]),
+            Err(CpuError::NegativeAddress { pc: 0, addr: -1 })
+        );
+
+        let mut big = BigCpu::new([3, 0, 99]);
+        big.poke(1, BigInt::from(i64::MAX) * 4);
+        assert_eq!(big.run(vec![1.into()]), Err(CpuError::Overflow { pc: 0 }));
+
+        let mut big = BigCpu::new([1101, 1, 1, 0, 42]);
+        assert_eq!(
+            big.run(vec![]),
+            Err(CpuError::InvalidOpCode { pc: 4, code: 42 })
+        );
+        assert_eq!(big.peek(0), BigInt::from(2));
+    }
+}
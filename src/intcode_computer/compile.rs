@@ -0,0 +1,319 @@
+//! Ahead-of-time compilation of Intcode programs to Rust.
+//!
+//! [`compile`] turns every basic block of the [`Cfg`] into one arm of a dispatch loop. Jumps to
+//! addresses that were not compiled and writes into compiled code hand the machine over to the
+//! interpreter, so self-modifying programs still run correctly, just not any faster.
+use super::analysis::Cfg;
+use super::disasm::{Instruction, Param};
+use super::opcode::OpCode;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Support functions for the generated code.
+pub mod runtime {
+    use crate::intcode_computer::cpu::{Cpu, Event};
+    pub use crate::intcode_computer::error::CpuError;
+    pub use crate::intcode_computer::memory::{FlatMemory, Memory};
+
+    #[inline]
+    pub fn address(value: i64, pc: usize) -> Result<usize, CpuError> {
+        usize::try_from(value).map_err(|_| CpuError::NegativeAddress { pc, addr: value })
+    }
+
+    #[inline]
+    pub fn relative(relative_base: i64, offset: i64, pc: usize) -> Result<i64, CpuError> {
+        relative_base
+            .checked_add(offset)
+            .ok_or(CpuError::Overflow { pc })
+    }
+
+    #[inline]
+    pub fn read(memory: &FlatMemory, addr: i64, pc: usize) -> Result<i64, CpuError> {
+        Ok(memory.get(address(addr, pc)?))
+    }
+
+    /// Writes `value` to `addr` and returns the address.
+    #[inline]
+    pub fn write(
+        memory: &mut FlatMemory,
+        addr: i64,
+        value: i64,
+        pc: usize,
+    ) -> Result<usize, CpuError> {
+        let addr = address(addr, pc)?;
+        memory.set(addr, value);
+        Ok(addr)
+    }
+
+    /// Continues running the program in the interpreter from `pc`.
+    pub fn fallback(
+        memory: FlatMemory,
+        pc: usize,
+        relative_base: i64,
+        mut input: impl FnMut() -> i64,
+        mut output: impl FnMut(i64),
+    ) -> Result<(), CpuError> {
+        let mut cpu = Cpu::from_state(memory, pc, relative_base as isize);
+        loop {
+            match cpu.resume()? {
+                Event::Output(value) => output(value),
+                Event::NeedsInput => cpu.provide_input(input()),
+                Event::Halted => return Ok(()),
+            }
+        }
+    }
+}
+
+const INDENT: &str = "                ";
+
+fn operand(param: &Param, pc: usize) -> String {
+    match param {
+        Param::Immediate(value) => value.to_string(),
+        Param::Position(addr) => format!("read(&mem, {addr}, {pc})?"),
+        Param::Relative(offset) => format!("read(&mem, relative(rb, {offset}, {pc})?, {pc})?"),
+    }
+}
+
+fn fallback(pc: usize) -> String {
+    format!("{INDENT}return fallback(mem, {pc}, rb, input, output);\n")
+}
+
+/// Compiles one basic block, ending with the expression for the address to continue at.
+/// Sets `checks_code` if a write needs the `is_code` check at runtime.
+fn block(instructions: &[Instruction], code: &BTreeSet<usize>, checks_code: &mut bool) -> String {
+    let mut out = String::new();
+    for instruction in instructions {
+        writeln!(out, "{INDENT}// {:>5}: {instruction}", instruction.addr()).unwrap();
+        let Instruction::Op {
+            addr: pc,
+            opcode,
+            params,
+        } = instruction
+        else {
+            out.push_str(&fallback(instruction.addr()));
+            return out;
+        };
+        let pc = *pc;
+        let next = pc + instruction.size();
+        if opcode.writes() && matches!(params.last(), Some(Param::Immediate(_))) {
+            out.push_str(&fallback(pc));
+            return out;
+        }
+
+        let a = params.first().map(|param| operand(param, pc));
+        let b = params.get(1).map(|param| operand(param, pc));
+        let constant = match params[..] {
+            [Param::Immediate(x), Param::Immediate(y), _] => match opcode {
+                OpCode::Add => x.checked_add(y),
+                OpCode::Mul => x.checked_mul(y),
+                OpCode::Lt => Some((x < y) as i64),
+                _ => Some((x == y) as i64),
+            },
+            _ => None,
+        };
+        let value = match opcode {
+            _ if constant.is_some() => constant.map(|value| value.to_string()),
+            OpCode::Add | OpCode::Mul => {
+                let op = if *opcode == OpCode::Add { "add" } else { "mul" };
+                Some(format!(
+                    "i64::checked_{op}({}, {}).ok_or(CpuError::Overflow {{ pc: {pc} }})?",
+                    a.unwrap(),
+                    b.unwrap()
+                ))
+            }
+            OpCode::Lt => Some(format!("({} < {}) as i64", a.unwrap(), b.unwrap())),
+            OpCode::Eq => Some(format!("({} == {}) as i64", a.unwrap(), b.unwrap())),
+            OpCode::In => Some("input()".to_string()),
+            OpCode::Out => {
+                writeln!(out, "{INDENT}output({});", a.unwrap()).unwrap();
+                None
+            }
+            OpCode::Rb => {
+                writeln!(
+                    out,
+                    "{INDENT}rb = i64::checked_add(rb, {}).ok_or(CpuError::Overflow {{ pc: {pc} }})?;",
+                    a.unwrap()
+                )
+                .unwrap();
+                None
+            }
+            OpCode::Halt => {
+                writeln!(out, "{INDENT}return Ok(());").unwrap();
+                return out;
+            }
+            OpCode::Jt | OpCode::Jf => {
+                let target = match params[1] {
+                    Param::Immediate(target) if target >= 0 => target.to_string(),
+                    _ => format!("address({}, {pc})?", b.unwrap()),
+                };
+                let jump = |cond: bool| cond == (*opcode == OpCode::Jt);
+                match params[0] {
+                    Param::Immediate(cond) if jump(cond != 0) => {
+                        writeln!(out, "{INDENT}{target}").unwrap()
+                    }
+                    Param::Immediate(_) => writeln!(out, "{INDENT}{next}").unwrap(),
+                    _ => {
+                        let op = if *opcode == OpCode::Jt { "!=" } else { "==" };
+                        writeln!(
+                            out,
+                            "{INDENT}if {} {op} 0 {{ {target} }} else {{ {next} }}",
+                            a.unwrap()
+                        )
+                        .unwrap()
+                    }
+                }
+                return out;
+            }
+        };
+
+        let Some(value) = value else { continue };
+        writeln!(out, "{INDENT}let value = {value};").unwrap();
+        match params.last().unwrap() {
+            Param::Position(addr) => {
+                writeln!(out, "{INDENT}write(&mut mem, {addr}, value, {pc})?;").unwrap();
+                if *addr >= 0 && code.contains(&(*addr as usize)) {
+                    out.push_str(&fallback(next));
+                    return out;
+                }
+            }
+            Param::Relative(offset) => {
+                *checks_code = true;
+                writeln!(
+                    out,
+                    "{INDENT}let addr = write(&mut mem, relative(rb, {offset}, {pc})?, value, {pc})?;"
+                )
+                .unwrap();
+                writeln!(out, "{INDENT}if is_code(addr) {{").unwrap();
+                write!(out, "    {}", fallback(next)).unwrap();
+                writeln!(out, "{INDENT}}}").unwrap();
+            }
+            Param::Immediate(_) => unreachable!("checked above"),
+        }
+    }
+
+    let end = instructions
+        .last()
+        .map_or(0, |last| last.addr() + last.size());
+    writeln!(out, "{INDENT}{end}").unwrap();
+    out
+}
+
+/// Generates a Rust module with a `run(input, output)` function that executes `program`. The
+/// module only depends on this crate, see [`runtime`].
+pub fn compile(program: &[i64]) -> String {
+    let cfg = Cfg::build(program);
+    let code = cfg.code();
+    let mut checks_code = false;
+    let arms: Vec<_> = cfg
+        .blocks
+        .values()
+        .map(|b| (b.start, block(&b.instructions, &code, &mut checks_code)))
+        .collect();
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "//! Compiled from an Intcode program by `cargo intcode compile`, do not edit."
+    )
+    .unwrap();
+    // generated code is correct but not idiomatic, e.g. blocks that end the program leave
+    // assignments unused and the dispatch loop unreachable.
+    writeln!(
+        out,
+        "#![allow(unused_assignments, unused_mut, unreachable_code, clippy::all)]"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "use advent_of_code::intcode_computer::compile::runtime::*;\n"
+    )
+    .unwrap();
+
+    writeln!(out, "const PROGRAM: &[i64] = &[").unwrap();
+    for chunk in program.chunks(12) {
+        let words: Vec<_> = chunk.iter().map(|word| word.to_string()).collect();
+        writeln!(out, "    {},", words.join(", ")).unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    if checks_code {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for &addr in &code {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == addr => *end = addr,
+                _ => ranges.push((addr, addr)),
+            }
+        }
+        let ranges: Vec<_> = ranges
+            .iter()
+            .map(|(start, end)| format!("{start}..={end}"))
+            .collect();
+        writeln!(out, "/// Whether `addr` is part of a compiled instruction.").unwrap();
+        writeln!(out, "fn is_code(addr: usize) -> bool {{").unwrap();
+        writeln!(out, "    matches!(addr, {})", ranges.join(" | ")).unwrap();
+        writeln!(out, "}}\n").unwrap();
+    }
+
+    writeln!(out, "pub fn run(").unwrap();
+    writeln!(out, "    mut input: impl FnMut() -> i64,").unwrap();
+    writeln!(out, "    mut output: impl FnMut(i64),").unwrap();
+    writeln!(out, ") -> Result<(), CpuError> {{").unwrap();
+    writeln!(
+        out,
+        "    let mut mem: FlatMemory = PROGRAM.iter().copied().collect();"
+    )
+    .unwrap();
+    writeln!(out, "    let mut rb: i64 = 0;").unwrap();
+    writeln!(out, "    let mut pc: usize = 0;").unwrap();
+    writeln!(out, "    loop {{").unwrap();
+    writeln!(out, "        pc = match pc {{").unwrap();
+    for (start, body) in arms {
+        writeln!(out, "            {start} => {{").unwrap();
+        out.push_str(&body);
+        writeln!(out, "            }}").unwrap();
+    }
+    writeln!(
+        out,
+        "            _ => return fallback(mem, pc, rb, input, output),"
+    )
+    .unwrap();
+    writeln!(out, "        }};").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+#[path = "../../benches/compiled/countdown.rs"]
+mod countdown;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{cpu::Cpu, program::Program};
+
+    #[test]
+    fn test_compile() {
+        let program = Program::load("benches/compiled/countdown.txt")
+            .unwrap()
+            .into_vec();
+        let source = compile(&program);
+        assert_eq!(
+            source,
+            include_str!("../../benches/compiled/countdown.rs"),
+            "regenerate benches/compiled/countdown.rs with `cargo intcode compile`"
+        );
+        // the program patches its `halt` at 21 into an `out`, which only the interpreter sees.
+        assert!(source.contains("return fallback(mem, 21, rb, input, output);"));
+
+        for counter in [1, 5, 50] {
+            let mut cpu = Cpu::new(program.clone());
+            cpu.run(Some(vec![counter])).unwrap();
+            let mut outputs = Vec::new();
+            countdown::run(|| counter, |value| outputs.push(value)).unwrap();
+            assert_eq!(outputs, cpu.outputs);
+            assert_eq!(outputs, vec![7]);
+        }
+    }
+}
@@ -1,23 +1,37 @@
+use super::error::CpuError;
+use super::io::{IntcodeInput, IntcodeOutput, IterInput, StdinInput};
+use super::memory::{FlatMemory, Memory};
 use super::opcode::OpCode;
 use super::opcode::OpCode::*;
-use std::{collections::HashMap, io::stdin};
-use tokio::sync::mpsc::{self, Receiver, Sender};
-use tracing::info;
+use super::profile::Profile;
+use super::snapshot::Snapshot;
+use super::trace::TraceStep;
+use super::watch::{Hit, Watch, Watched};
+use std::{
+    cell::Cell,
+    collections::{hash_map::DefaultHasher, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::{debug, info_span, Instrument};
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 enum Dat {
-    Position(usize),
+    Position(i64),
     Literal(i64),
-    Relative(isize),
+    Relative(i64),
 }
 
-impl From<(u8, i64)> for Dat {
-    fn from((mode, value): (u8, i64)) -> Self {
+impl TryFrom<(u8, i64)> for Dat {
+    type Error = u8;
+
+    fn try_from((mode, value): (u8, i64)) -> Result<Self, Self::Error> {
         match mode {
-            0 => Self::Position(value as usize),
-            1 => Self::Literal(value),
-            2 => Self::Relative(value as isize),
-            _ => unreachable!(),
+            0 => Ok(Self::Position(value)),
+            1 => Ok(Self::Literal(value)),
+            2 => Ok(Self::Relative(value)),
+            _ => Err(mode),
         }
     }
 }
@@ -28,167 +42,927 @@ pub enum Msg {
     RxRequest,
 }
 
-impl Dat {
-    fn value(&self, program: &HashMap<usize, i64>, base: &isize) -> i64 {
-        match self {
-            Dat::Position(v) => *program.get(v).unwrap_or(&0),
-            Dat::Literal(v) => *v,
-            Dat::Relative(v) => *program.get(&((base + v) as usize)).unwrap_or(&0),
-        }
-    }
+/// Default for [`Cpu::max_steps`], far above what any puzzle input needs.
+pub const DEFAULT_MAX_STEPS: u64 = 1_000_000_000;
 
-    fn addr(&self, base: &isize) -> usize {
-        match self {
-            Dat::Position(v) => *v,
-            Dat::Relative(v) => (base + v) as usize,
-            _ => panic!("tried to get addr of a non positional or relative argument"),
-        }
-    }
+/// Instructions at or above this address are never cached by [`Engine::Predecoded`].
+const DECODE_LIMIT: usize = 1 << 20;
+
+/// How a [`Cpu`] fetches instructions, see [`Cpu::set_engine`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Decodes the opcode and parameter modes from memory on every step.
+    #[default]
+    Interpreter,
+    /// Caches decoded instructions by address and drops them when one of their words is
+    /// written. Writes that bypass the cpu, e.g. through [`Cpu::memory`], are not noticed;
+    /// use [`Cpu::poke`] instead.
+    Predecoded,
+}
+
+/// Counters logged when a machine halts, see [`Cpu::summary`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub steps: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// The highest address an instruction was fetched from, read or written.
+    pub max_addr: usize,
 }
 
-impl Dat {}
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    NeedsInput,
+    Output(i64),
+    Halted,
+}
 
-pub struct Cpu {
-    pub program: HashMap<usize, i64>,
+/// The complete state of an Intcode machine. Clones run independently, see [`Cpu::fork`].
+///
+/// Words are `i64` and arithmetic that leaves its range fails with [`CpuError::Overflow`]. With
+/// the `bigint` feature, `bigint::BigCpu` runs programs on arbitrary-precision words instead.
+#[derive(Clone)]
+pub struct Cpu<M: Memory = FlatMemory> {
+    pub memory: M,
     pc: usize,
+    op_pc: usize,
     pub relative_base: isize,
     pub outputs: Vec<i64>,
-    input: Option<Receiver<Msg>>,
+    pending_inputs: VecDeque<i64>,
+    pub trace: Option<Vec<TraceStep>>,
+    pub profile: Option<Profile>,
+    pub max_steps: Option<u64>,
+    pub detect_loops: bool,
+    /// Conditions that interrupt [`Cpu::step_watched`] and [`Cpu::resume_watched`].
+    pub watches: Vec<Watch>,
+    steps: u64,
+    id: usize,
+    inputs: u64,
+    output_count: u64,
+    max_addr: Cell<usize>,
+    seen_states: HashSet<u64>,
+    hits: VecDeque<Hit>,
+    watched_write: Option<(usize, i64)>,
+    /// The pc of a [`Watch::Pc`] hit whose instruction has not been executed yet.
+    reported_pc: Option<usize>,
+    /// Whether the summary was logged, so re-stepping a halted machine does not repeat it.
+    logged_halt: bool,
+    engine: Engine,
+    decoded: Vec<Option<(OpCode, Dat, Dat, Dat)>>,
 }
 
 impl Cpu {
     pub fn new(program: Vec<i64>) -> Self {
-        let program: HashMap<usize, i64> = program.into_iter().enumerate().collect();
+        Self::with_memory(program)
+    }
+}
+
+impl<M: Memory> Cpu<M> {
+    pub fn with_memory(program: Vec<i64>) -> Self {
+        Self::from_state(program.into_iter().collect(), 0, 0)
+    }
+
+    /// Creates a machine that continues a program already in progress, at `pc` with the given
+    /// memory and relative base.
+    pub fn from_state(memory: M, pc: usize, relative_base: isize) -> Self {
         Self {
-            program,
-            pc: 0,
-            relative_base: 0,
+            memory,
+            pc,
+            op_pc: pc,
+            relative_base,
             outputs: Vec::new(),
-            input: None,
+            pending_inputs: VecDeque::new(),
+            trace: None,
+            profile: None,
+            max_steps: Some(DEFAULT_MAX_STEPS),
+            detect_loops: false,
+            watches: Vec::new(),
+            steps: 0,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            inputs: 0,
+            output_count: 0,
+            max_addr: Cell::new(0),
+            seen_states: HashSet::new(),
+            hits: VecDeque::new(),
+            watched_write: None,
+            reported_pc: None,
+            logged_halt: false,
+            engine: Engine::default(),
+            decoded: Vec::new(),
         }
     }
 
-    pub fn new_async(program: Vec<i64>) -> (Self, Sender<Msg>) {
-        let (tx_handle, rx) = mpsc::channel(32);
-        let mut instance = Self::new(program);
-        instance.input = Some(rx);
-        (instance, tx_handle)
+    fn addr(&self, arg: &Dat) -> Result<usize, CpuError> {
+        let addr = match arg {
+            Dat::Position(v) => *v,
+            Dat::Relative(v) => self.checked((self.relative_base as i64).checked_add(*v))?,
+            Dat::Literal(_) => return Err(CpuError::WriteToImmediate { pc: self.op_pc }),
+        };
+        self.to_addr(addr)
     }
 
-    fn get(&self, arg: &Dat) -> i64 {
-        arg.value(&self.program, &self.relative_base)
+    fn to_addr(&self, addr: i64) -> Result<usize, CpuError> {
+        let addr = usize::try_from(addr).map_err(|_| CpuError::NegativeAddress {
+            pc: self.op_pc,
+            addr,
+        })?;
+        self.max_addr.set(self.max_addr.get().max(addr));
+        Ok(addr)
     }
 
-    fn get_mut(&mut self, arg: &Dat) -> &mut i64 {
-        self.program
-            .entry(arg.addr(&self.relative_base))
-            .or_default()
+    /// Reports an overflowing arithmetic result of the current instruction.
+    fn checked<T>(&self, value: Option<T>) -> Result<T, CpuError> {
+        value.ok_or(CpuError::Overflow { pc: self.op_pc })
     }
 
-    fn run_common(&mut self) -> (OpCode, Dat) {
-        let (opcode, a, b, c) = self.advance();
+    fn get(&self, arg: &Dat) -> Result<i64, CpuError> {
+        match arg {
+            Dat::Literal(v) => Ok(*v),
+            _ => Ok(self.memory.get(self.addr(arg)?)),
+        }
+    }
+
+    fn get_mut(&mut self, arg: &Dat) -> Result<&mut i64, CpuError> {
+        let addr = self.addr(arg)?;
+        if let Some(profile) = self.profile.as_mut() {
+            profile.writes += 1;
+        }
+        self.invalidate(addr);
+        Ok(self.memory.get_mut(addr))
+    }
+
+    /// Drops every cached instruction that `addr` is part of.
+    fn invalidate(&mut self, addr: usize) {
+        if addr >= self.decoded.len() + 3 {
+            return;
+        }
+        for start in addr.saturating_sub(3)..=addr {
+            if let Some(slot) = self.decoded.get_mut(start) {
+                *slot = None;
+            }
+        }
+    }
+
+    fn run_common(&mut self) -> Result<(OpCode, Dat), CpuError> {
+        let (opcode, a, b, c) = self.advance()?;
+
+        if let Some(profile) = self.profile.as_mut() {
+            let reads = [&a, &b, &c][..opcode.reads()]
+                .iter()
+                .filter(|arg| !matches!(arg, Dat::Literal(_)))
+                .count();
+            profile.record(self.op_pc, opcode, reads);
+        }
+
+        if self.trace.is_some() {
+            let operands = [&a, &b, &c][..opcode.reads()]
+                .iter()
+                .map(|arg| self.get(arg))
+                .collect::<Result<_, _>>()?;
+            if let Some(trace) = self.trace.as_mut() {
+                trace.push(TraceStep {
+                    pc: self.op_pc,
+                    opcode,
+                    operands,
+                    writes: Vec::new(),
+                    input: None,
+                    output: None,
+                });
+            }
+        }
+
+        let relative_base = self.relative_base;
+        if !self.watches.is_empty() {
+            self.watch_operands(opcode, [&a, &b, &c]);
+        }
 
         match opcode {
-            Add => *self.get_mut(&c) = self.get(&a) + self.get(&b),
-            Mul => *self.get_mut(&c) = self.get(&a) * self.get(&b),
+            Add => {
+                let value = self.get(&a)?.checked_add(self.get(&b)?);
+                *self.get_mut(&c)? = self.checked(value)?
+            }
+            Mul => {
+                let value = self.get(&a)?.checked_mul(self.get(&b)?);
+                *self.get_mut(&c)? = self.checked(value)?
+            }
             Jt => {
-                if self.get(&a) != 0 {
-                    self.pc = self.get(&b) as usize
+                if self.get(&a)? != 0 {
+                    self.pc = self.to_addr(self.get(&b)?)?
                 }
             }
             Jf => {
-                if self.get(&a) == 0 {
-                    self.pc = self.get(&b) as usize
+                if self.get(&a)? == 0 {
+                    self.pc = self.to_addr(self.get(&b)?)?
                 }
             }
-            Lt => *self.get_mut(&c) = (self.get(&a) < self.get(&b)) as i64,
-            Eq => *self.get_mut(&c) = (self.get(&a) == self.get(&b)) as i64,
-            Rb => self.relative_base += self.get(&a) as isize,
+            Lt => *self.get_mut(&c)? = (self.get(&a)? < self.get(&b)?) as i64,
+            Eq => *self.get_mut(&c)? = (self.get(&a)? == self.get(&b)?) as i64,
+            Rb => {
+                let value = self.relative_base.checked_add(self.get(&a)? as isize);
+                self.relative_base = self.checked(value)?
+            }
             Halt | In | Out => (),
         }
-        (opcode, a)
+        if matches!(opcode, Add | Mul | Lt | Eq) {
+            self.record_write(&c)?;
+        }
+        if opcode == Rb && self.watches.contains(&Watch::RelativeBase) {
+            self.hits.push_back(Hit::RelativeBase {
+                pc: self.op_pc,
+                old: relative_base,
+                new: self.relative_base,
+            });
+        }
+        Ok((opcode, a))
+    }
+
+    /// Records hits for the operands read by the current instruction, and remembers the old
+    /// value of a watched write target.
+    fn watch_operands(&mut self, opcode: OpCode, args: [&Dat; 3]) {
+        let pc = self.op_pc;
+
+        for arg in &args[..opcode.reads()] {
+            if matches!(arg, Dat::Literal(_)) {
+                continue;
+            }
+            let Ok(addr) = self.addr(arg) else { continue };
+            let watched = self.watches.iter().any(|watch| match watch {
+                Watch::Read(range) => range.contains(&addr),
+                _ => false,
+            });
+            if watched {
+                let value = self.memory.get(addr);
+                self.hits.push_back(Hit::Read { pc, addr, value });
+            }
+        }
+
+        let target = match opcode {
+            In => Some(args[0]),
+            _ if opcode.writes() => Some(args[2]),
+            _ => None,
+        };
+        if let Some(Ok(addr)) = target.map(|arg| self.addr(arg)) {
+            let watched = self.watches.iter().any(|watch| match watch {
+                Watch::Write(range) => range.contains(&addr),
+                _ => false,
+            });
+            if watched {
+                self.watched_write = Some((addr, self.memory.get(addr)));
+            }
+        }
     }
 
-    pub fn run(&mut self, inputs: Option<Vec<i64>>) {
-        let mut inputs = inputs.map(|v| v.into_iter());
+    /// Runs until the program halts, reading inputs from `inputs` or, if `None`, from stdin.
+    /// Outputs are collected in [`Cpu::outputs`].
+    pub fn run(&mut self, inputs: Option<Vec<i64>>) -> Result<(), CpuError> {
+        let mut outputs = std::mem::take(&mut self.outputs);
+        let result = match inputs {
+            Some(inputs) => self.run_with(IterInput(inputs.into_iter()), &mut outputs),
+            None => self.run_with(StdinInput, &mut outputs),
+        };
+        self.outputs = outputs;
+        result
+    }
+
+    /// Runs until the program halts, reading from `input` and writing to `output`. Values
+    /// queued with [`Cpu::provide_input`] are read first.
+    pub fn run_with(
+        &mut self,
+        input: impl IntcodeInput,
+        output: impl IntcodeOutput,
+    ) -> Result<(), CpuError> {
+        self.run_device(&mut (input, output))
+    }
 
+    /// Runs until the program halts, connected to a peripheral that handles both input and
+    /// output.
+    pub fn run_device(
+        &mut self,
+        device: &mut (impl IntcodeInput + IntcodeOutput),
+    ) -> Result<(), CpuError> {
+        let _span = info_span!("run", id = self.id).entered();
         loop {
-            let (opcode, a) = self.run_common();
+            let (opcode, a) = self.run_common()?;
+            self.hits.clear();
             match opcode {
-                In => *self.get_mut(&a) = self.input(&mut inputs),
-                Out => self.output(self.get(&a)),
-                Halt => break,
+                In => {
+                    let value = match self.pending_inputs.pop_front() {
+                        Some(value) => value,
+                        None => device.read().map_err(|e| e.at(self.op_pc))?,
+                    };
+                    self.store_input(&a, value)?;
+                }
+                Out => {
+                    let value = self.get(&a)?;
+                    self.emit(value);
+                    device.write(value).map_err(|e| e.at(self.op_pc))?;
+                }
+                Halt => {
+                    self.halted();
+                    break;
+                }
                 _ => (),
             }
         }
+        Ok(())
     }
 
-    pub async fn run_async(&mut self, tx: Sender<Msg>) {
+    /// Runs until the program halts, sending outputs and a [`Msg::RxRequest`] before every
+    /// input on `tx`, and reading inputs from `rx`.
+    pub async fn run_async(
+        &mut self,
+        tx: Sender<Msg>,
+        rx: &mut Receiver<Msg>,
+    ) -> Result<(), CpuError> {
+        let span = info_span!("run_async", id = self.id);
+        self.run_async_inner(tx, rx).instrument(span).await
+    }
+
+    async fn run_async_inner(
+        &mut self,
+        tx: Sender<Msg>,
+        rx: &mut Receiver<Msg>,
+    ) -> Result<(), CpuError> {
         loop {
-            let (opcode, a) = self.run_common();
+            let (opcode, a) = self.run_common()?;
+            self.hits.clear();
             match opcode {
-                In => *self.get_mut(&a) = self.input_async(&tx).await,
-                Out => self.output_async(self.get(&a), &tx).await,
-                Halt => break,
+                In => {
+                    let value = self.input_async(&tx, rx).await?;
+                    self.store_input(&a, value)?;
+                }
+                Out => self.output_async(self.get(&a)?, &tx).await?,
+                Halt => {
+                    self.halted();
+                    break;
+                }
                 _ => (),
             }
         }
+        Ok(())
     }
 
-    fn advance(&mut self) -> (OpCode, Dat, Dat, Dat) {
-        let opcode = *self.program.entry(self.pc).or_default();
-        let a = *self.program.entry(self.pc + 1).or_default();
-        let b = *self.program.entry(self.pc + 2).or_default();
-        let c = *self.program.entry(self.pc + 3).or_default();
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            outputs: self.outputs.clone(),
+            pending_inputs: self.pending_inputs.iter().copied().collect(),
+        }
+    }
 
-        let a = Dat::from((((opcode / 100) % 10) as u8, a));
-        let b = Dat::from((((opcode / 1_000) % 10) as u8, b));
-        let c = Dat::from((((opcode / 10_000) % 10) as u8, c));
+    /// Resets the machine to a previously taken [`Snapshot`]: memory, pc, relative base, outputs
+    /// and pending inputs are replaced, and the decode cache, loop detection and pending watch
+    /// hits are cleared. Settings like the watches, step limit and engine are kept, as are the
+    /// trace and profile recordings and the counters of [`Cpu::summary`].
+    pub fn restore(&mut self, snapshot: Snapshot<M>) {
+        self.memory = snapshot.memory;
+        self.pc = snapshot.pc;
+        self.op_pc = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.outputs = snapshot.outputs;
+        self.pending_inputs = snapshot.pending_inputs.into();
+        self.seen_states.clear();
+        self.hits.clear();
+        self.watched_write = None;
+        self.reported_pc = None;
+        self.logged_halt = false;
+        self.decoded.clear();
+    }
 
-        let opcode = OpCode::from(opcode);
+    /// Starts counting executed instructions into [`Cpu::profile`].
+    pub fn record_profile(&mut self) {
+        self.profile = Some(Profile::default());
+    }
 
-        match opcode {
-            In | Out | Rb => self.pc += 2,
-            Jt | Jf => self.pc += 3,
-            Add | Mul | Lt | Eq => self.pc += 4,
-            Halt => (),
+    /// Starts recording every executed instruction into [`Cpu::trace`].
+    pub fn record_trace(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
+    fn record_write(&mut self, arg: &Dat) -> Result<(), CpuError> {
+        if let Some((addr, old)) = self.watched_write.take() {
+            self.hits.push_back(Hit::Write {
+                pc: self.op_pc,
+                addr,
+                old,
+                new: self.memory.get(addr),
+            });
+        }
+        if self.trace.is_some() {
+            let addr = self.addr(arg)?;
+            let value = self.memory.get(addr);
+            if let Some(step) = self.trace.as_mut().and_then(|trace| trace.last_mut()) {
+                step.writes.push((addr, value));
+            }
+        }
+        Ok(())
+    }
+
+    fn store_input(&mut self, arg: &Dat, value: i64) -> Result<(), CpuError> {
+        *self.get_mut(arg)? = value;
+        self.inputs += 1;
+        self.seen_states.clear();
+        if let Some(step) = self.trace.as_mut().and_then(|trace| trace.last_mut()) {
+            step.input = Some(value);
+        }
+        self.record_write(arg)
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn peek(&self, addr: usize) -> i64 {
+        self.memory.get(addr)
+    }
+
+    pub fn poke(&mut self, addr: usize, value: i64) {
+        self.invalidate(addr);
+        self.memory.set(addr, value);
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+        self.decoded.clear();
+    }
+
+    pub fn pending_inputs(&self) -> usize {
+        self.pending_inputs.len()
+    }
+
+    /// Whether the machine is paused at an `in` instruction without a queued input, e.g. after
+    /// [`Event::NeedsInput`].
+    pub fn waiting_for_input(&self) -> bool {
+        self.pending_inputs.is_empty() && self.memory.get(self.pc) % 100 == In.code()
+    }
+
+    /// Duplicates a machine that is [waiting for input](Cpu::waiting_for_input) and feeds
+    /// `input` to the copy, leaving this machine untouched. Useful to try every input from the
+    /// same state in a search.
+    pub fn fork(&self, input: i64) -> Option<Self> {
+        if !self.waiting_for_input() {
+            return None;
         }
+        let mut fork = self.clone();
+        fork.id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        fork.provide_input(input);
+        Some(fork)
+    }
+
+    pub fn provide_input(&mut self, value: i64) {
+        self.pending_inputs.push_back(value);
+    }
+
+    /// Runs until the program produces an output, halts, or reads an input that has not been
+    /// provided yet. In the latter case the `In` instruction is retried on the next call.
+    pub fn resume(&mut self) -> Result<Event, CpuError> {
+        loop {
+            if let Some(event) = self.step()? {
+                return Ok(event);
+            }
+        }
+    }
 
-        (opcode, a, b, c)
+    /// Executes a single instruction, returning the [`Event`] it caused, if any.
+    pub fn step(&mut self) -> Result<Option<Event>, CpuError> {
+        let event = self.execute();
+        self.hits.clear();
+        event
     }
 
-    fn input(&mut self, inputs: &mut Option<impl Iterator<Item = i64>>) -> i64 {
-        if let Some(inputs) = inputs {
-            inputs.next().unwrap()
-        } else {
-            let mut input_line = String::new();
-            stdin()
-                .read_line(&mut input_line)
-                .expect("Failed to read line");
+    /// Like [`Cpu::step`], but also stops for [`Cpu::watches`]. A watched pc is reported before
+    /// its instruction is executed, every other hit after the instruction and its event, one
+    /// per call.
+    pub fn step_watched(&mut self) -> Result<Option<Watched>, CpuError> {
+        if let Some(hit) = self.hits.pop_front() {
+            return Ok(Some(Watched::Hit(hit)));
+        }
+        let pc = self.pc;
+        if self.reported_pc != Some(pc) && self.watches.contains(&Watch::Pc(pc)) {
+            self.reported_pc = Some(pc);
+            return Ok(Some(Watched::Hit(Hit::Pc(pc))));
+        }
+
+        let event = self.execute()?.map(Watched::Event);
+        Ok(event.or_else(|| self.hits.pop_front().map(Watched::Hit)))
+    }
+
+    /// Like [`Cpu::resume`], but also stops for [`Cpu::watches`], see [`Cpu::step_watched`].
+    pub fn resume_watched(&mut self) -> Result<Watched, CpuError> {
+        loop {
+            if let Some(watched) = self.step_watched()? {
+                return Ok(watched);
+            }
+        }
+    }
+
+    fn execute(&mut self) -> Result<Option<Event>, CpuError> {
+        let (opcode, a) = self.run_common()?;
+        let event = match opcode {
+            In => match self.pending_inputs.pop_front() {
+                Some(value) => {
+                    self.store_input(&a, value)?;
+                    None
+                }
+                None => {
+                    // the instruction is retried later, so it must not count against the budget.
+                    self.pc = self.op_pc;
+                    self.steps -= 1;
+                    self.seen_states.clear();
+                    if let Some(trace) = self.trace.as_mut() {
+                        trace.pop();
+                    }
+                    if let Some(profile) = self.profile.as_mut() {
+                        profile.retract(self.pc);
+                    }
+                    self.hits.clear();
+                    self.watched_write = None;
+                    return Ok(Some(Event::NeedsInput));
+                }
+            },
+            Out => {
+                let value = self.get(&a)?;
+                self.output(value);
+                Some(Event::Output(value))
+            }
+            Halt => {
+                self.seen_states.clear();
+                self.halted();
+                Some(Event::Halted)
+            }
+            _ => None,
+        };
+        self.reported_pc = None;
+        Ok(event)
+    }
 
-            input_line
-                .trim()
-                .parse::<i64>()
-                .expect("Could not parse integer from input")
+    /// Counts the instruction about to be executed against [`Cpu::max_steps`] and, if
+    /// [`Cpu::detect_loops`] is set, fails when the machine state repeats without any I/O in
+    /// between.
+    fn check_step(&mut self) -> Result<(), CpuError> {
+        if self
+            .max_steps
+            .is_some_and(|max_steps| self.steps >= max_steps)
+        {
+            return Err(CpuError::StepLimit {
+                pc: self.pc,
+                steps: self.steps,
+            });
         }
+        self.steps += 1;
+
+        if self.detect_loops {
+            let mut hasher = DefaultHasher::new();
+            (self.pc, self.relative_base, self.memory.words()).hash(&mut hasher);
+            if !self.seen_states.insert(hasher.finish()) {
+                return Err(CpuError::InfiniteLoop { pc: self.pc });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Identifies the machine in log spans. Every machine, including a [fork](Cpu::fork), gets
+    /// its own.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            steps: self.steps,
+            inputs: self.inputs,
+            outputs: self.output_count,
+            max_addr: self.max_addr.get(),
+        }
+    }
+
+    fn halted(&mut self) {
+        if self.logged_halt {
+            return;
+        }
+        self.logged_halt = true;
+        let summary = self.summary();
+        debug!(
+            id = self.id,
+            steps = summary.steps,
+            inputs = summary.inputs,
+            outputs = summary.outputs,
+            max_addr = summary.max_addr,
+            "halted"
+        );
+    }
+
+    fn advance(&mut self) -> Result<(OpCode, Dat, Dat, Dat), CpuError> {
+        self.check_step()?;
+        self.op_pc = self.pc;
+        let instruction = match self.decoded.get(self.pc) {
+            Some(Some(instruction)) => *instruction,
+            _ => {
+                let instruction = self.decode()?;
+                if self.engine == Engine::Predecoded && self.pc < DECODE_LIMIT {
+                    if self.decoded.len() <= self.pc {
+                        self.decoded.resize(self.pc + 1, None);
+                    }
+                    self.decoded[self.pc] = Some(instruction);
+                }
+                instruction
+            }
+        };
+
+        let opcode = instruction.0;
+        let last = self.pc + opcode.params();
+        self.max_addr.set(self.max_addr.get().max(last));
+        #[cfg(feature = "intcode-trace")]
+        debug!(pc = self.pc, op = %opcode, relative_base = self.relative_base, "step");
+        if opcode != Halt {
+            self.pc += 1 + opcode.params();
+        }
+        Ok(instruction)
+    }
+
+    fn decode(&self) -> Result<(OpCode, Dat, Dat, Dat), CpuError> {
+        let opcode = self.memory.get(self.pc);
+        let a = self.memory.get(self.pc + 1);
+        let b = self.memory.get(self.pc + 2);
+        let c = self.memory.get(self.pc + 3);
+
+        let pc = self.pc;
+        let mode = |mode: i64, value: i64| {
+            Dat::try_from((mode as u8, value)).map_err(|mode| CpuError::InvalidMode { pc, mode })
+        };
+        let a = mode((opcode / 100) % 10, a)?;
+        let b = mode((opcode / 1_000) % 10, b)?;
+        let c = mode((opcode / 10_000) % 10, c)?;
+
+        let opcode =
+            OpCode::try_from(opcode).map_err(|code| CpuError::InvalidOpCode { pc, code })?;
+        Ok((opcode, a, b, c))
     }
 
-    async fn input_async(&mut self, tx: &Sender<Msg>) -> i64 {
-        tx.send(Msg::RxRequest).await.unwrap();
+    async fn input_async(
+        &mut self,
+        tx: &Sender<Msg>,
+        rx: &mut Receiver<Msg>,
+    ) -> Result<i64, CpuError> {
+        let pc = self.op_pc;
+        tx.send(Msg::RxRequest)
+            .await
+            .map_err(|_| CpuError::ChannelClosed { pc })?;
         loop {
-            if let Msg::Value(value) = self.input.as_mut().unwrap().recv().await.unwrap() {
-                return value;
+            match rx.recv().await {
+                Some(Msg::Value(value)) => return Ok(value),
+                Some(Msg::RxRequest) => (),
+                None => return Err(CpuError::ChannelClosed { pc }),
             }
         }
     }
 
     fn output(&mut self, value: i64) {
         self.outputs.push(value);
-        info!("{}", value)
+        self.emit(value);
+    }
+
+    fn emit(&mut self, value: i64) {
+        self.seen_states.clear();
+        self.output_count += 1;
+        if let Some(step) = self.trace.as_mut().and_then(|trace| trace.last_mut()) {
+            step.output = Some(value);
+        }
+        #[cfg(feature = "intcode-trace")]
+        debug!(value, "output");
     }
 
-    async fn output_async(&mut self, value: i64, tx: &Sender<Msg>) {
-        tx.send(Msg::Value(value)).await.unwrap();
+    async fn output_async(&mut self, value: i64, tx: &Sender<Msg>) -> Result<(), CpuError> {
+        tx.send(Msg::Value(value))
+            .await
+            .map_err(|_| CpuError::ChannelClosed { pc: self.op_pc })?;
         self.output(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+    use pathfinding::prelude::bfs;
+
+    #[test]
+    fn test_errors() {
+        let mut cpu = Cpu::new(vec![1101, 1, 1, 0, 42]);
+        assert_eq!(
+            cpu.run(None),
+            Err(CpuError::InvalidOpCode { pc: 4, code: 42 })
+        );
+
+        let mut cpu = Cpu::new(vec![11101, 1, 1, 0, 99]);
+        assert_eq!(cpu.run(None), Err(CpuError::WriteToImmediate { pc: 0 }));
+
+        let mut cpu = Cpu::new(vec![204, -1, 99]);
+        assert_eq!(
+            cpu.run(None),
+            Err(CpuError::NegativeAddress { pc: 0, addr: -1 })
+        );
+
+        let mut cpu = Cpu::new(vec![3, 0, 99]);
+        assert_eq!(
+            cpu.run(Some(vec![])),
+            Err(CpuError::InputExhausted { pc: 0 })
+        );
+
+        let mut cpu = Cpu::new(vec![1001, 5, 1, 0, 99, i64::MAX]);
+        assert_eq!(cpu.run(None), Err(CpuError::Overflow { pc: 0 }));
+
+        let mut cpu = Cpu::new(vec![1102, i64::MAX, 2, 0, 99]);
+        assert_eq!(cpu.run(None), Err(CpuError::Overflow { pc: 0 }));
+
+        let mut cpu = Cpu::new(vec![109, i64::MAX, 209, 1, 99]);
+        assert_eq!(cpu.run(None), Err(CpuError::Overflow { pc: 2 }));
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let program = assemble("loop: jt #1, #loop").unwrap();
+        let mut cpu = Cpu::new(program.clone());
+        cpu.max_steps = Some(1000);
+        assert_eq!(
+            cpu.run(None),
+            Err(CpuError::StepLimit { pc: 0, steps: 1000 })
+        );
+
+        let mut cpu = Cpu::new(program);
+        cpu.detect_loops = true;
+        assert_eq!(cpu.run(None), Err(CpuError::InfiniteLoop { pc: 0 }));
+    }
+
+    #[test]
+    fn test_step_limit_polling_input() {
+        let program = assemble(
+            "loop: in [x]
+                   out [x]
+                   jt [x], #loop
+                   halt
+             x:    data 0",
+        )
+        .unwrap();
+        let mut cpu = Cpu::new(program);
+        cpu.max_steps = Some(7);
+        cpu.record_profile();
+        for input in [1, 0] {
+            for _ in 0..10 {
+                assert_eq!(cpu.resume(), Ok(Event::NeedsInput));
+            }
+            cpu.provide_input(input);
+            assert_eq!(cpu.resume(), Ok(Event::Output(input)));
+        }
+        assert_eq!(cpu.resume(), Ok(Event::Halted));
+        assert_eq!(cpu.steps(), 7);
+        assert_eq!(cpu.profile.unwrap().instructions, 7);
+    }
+
+    #[test]
+    fn test_predecoded_self_modifying() {
+        let program = assemble(
+            "start: out #1
+                    add [flag], #1, [flag]
+                    mul #1, #2, [start+1]
+                    eq [flag], #2, [t]
+                    jf [t], #start
+                    halt
+             flag:  data 0
+             t:     data 0",
+        )
+        .unwrap();
+
+        for engine in [Engine::Interpreter, Engine::Predecoded] {
+            let mut cpu = Cpu::new(program.clone());
+            cpu.set_engine(engine);
+            cpu.run(None).unwrap();
+            assert_eq!(cpu.outputs, vec![1, 2]);
+        }
+    }
+
+    /// A combination lock that reads one digit at a time: it outputs 1 for a correct digit,
+    /// 0 and halts for a wrong one, and 2 once the whole combination was entered.
+    const LOCK: &str = "        rb #secret
+                loop:   in [d]
+                        eq [d], rb+0, [t]
+                        jf [t], #fail
+                        rb #1
+                        add [left], #-1, [left]
+                        jf [left], #open
+                        out #1
+                        jt #1, #loop
+                fail:   out #0
+                        halt
+                open:   out #2
+                        halt
+                d:      data 0
+                t:      data 0
+                left:   data 3
+                secret: data 4
+                        data 1
+                        data 3";
+
+    #[derive(Clone)]
+    struct Attempt {
+        digits: Vec<i64>,
+        cpu: Cpu,
+    }
+
+    impl PartialEq for Attempt {
+        fn eq(&self, other: &Self) -> bool {
+            self.digits == other.digits
+        }
+    }
+
+    impl std::cmp::Eq for Attempt {}
+
+    impl std::hash::Hash for Attempt {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.digits.hash(state);
+        }
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut cpu = Cpu::new(assemble(LOCK).unwrap());
+        assert!(!cpu.waiting_for_input());
+        assert!(cpu.fork(1).is_none());
+        assert_eq!(cpu.resume(), Ok(Event::NeedsInput));
+        assert!(cpu.waiting_for_input());
+
+        let mut fork = cpu.fork(4).unwrap();
+        assert_eq!(fork.resume(), Ok(Event::Output(1)));
+        assert!(cpu.waiting_for_input() && cpu.outputs.is_empty());
+
+        let start = Attempt {
+            digits: Vec::new(),
+            cpu,
+        };
+        let successors = |attempt: &Attempt| {
+            let mut next = Vec::new();
+            for digit in 0..10 {
+                let Some(mut cpu) = attempt.cpu.fork(digit) else {
+                    continue;
+                };
+                while let Ok(Event::Output(_)) = cpu.resume() {}
+                if cpu.outputs.last() != Some(&0) {
+                    let mut digits = attempt.digits.clone();
+                    digits.push(digit);
+                    next.push(Attempt { digits, cpu });
+                }
+            }
+            next
+        };
+        let path = bfs(&start, successors, |attempt| {
+            attempt.cpu.outputs.last() == Some(&2)
+        })
+        .unwrap();
+        assert_eq!(path.last().unwrap().digits, vec![4, 1, 3]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let program = assemble(
+            "    in [x]
+                 add [x], #1, [y]
+                 out [y]
+                 halt
+             x:  data 0
+             y:  data 0",
+        )
+        .unwrap();
+        let mut cpu = Cpu::new(program);
+        cpu.run(Some(vec![41])).unwrap();
+        assert_eq!(
+            cpu.summary(),
+            Summary {
+                steps: 4,
+                inputs: 1,
+                outputs: 1,
+                max_addr: 10,
+            }
+        );
+        assert!(cpu.logged_halt);
+        assert_eq!(cpu.resume(), Ok(Event::Halted));
+        cpu.restore(cpu.snapshot());
+        assert!(!cpu.logged_halt);
+
+        let mut cpu = Cpu::new(assemble("in [0]\nhalt").unwrap());
+        assert_eq!(cpu.resume(), Ok(Event::NeedsInput));
+        assert_ne!(cpu.fork(1).unwrap().id(), cpu.id());
     }
 }
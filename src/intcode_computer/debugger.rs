@@ -0,0 +1,308 @@
+use super::cpu::{Cpu, Event};
+use super::disasm::Instruction;
+use super::error::CpuError;
+use super::watch::{Hit, Watch, Watched};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+/// The most words `peek` prints at once.
+const MAX_PEEK: usize = 1000;
+
+const HELP: &str = "\
+commands:
+  s, step [n]           execute n instructions (default 1)
+  c, continue           run until a breakpoint, watchpoint, input request or halt
+  b, break [addr]       set a breakpoint, or list breakpoints
+  d, delete <addr>      remove a breakpoint
+  w, watch <addr> [n]   stop when any of n words starting at addr is written (default 1)
+  l, list [n]           disassemble n instructions from pc (default 5)
+  p, peek <addr> [n]    print n memory words starting at addr (default 1)
+  poke <addr> <value>   write value to addr
+  r, regs               show pc, relative base and pending inputs
+  i, input <v>[,v...]   queue input values
+  o, outputs            show all outputs so far
+  q, quit               leave the debugger";
+
+/// Why execution stopped in [`Debugger::step`] or [`Debugger::cont`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    Watch(Hit),
+    NeedsInput,
+    Halted,
+    Error(CpuError),
+}
+
+pub struct Debugger {
+    pub cpu: Cpu,
+    pub breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(cpu: Cpu) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Executes up to `n` instructions, stopping early on input requests, halts, errors,
+    /// breakpoints or watchpoints.
+    pub fn step(&mut self, n: usize, out: &mut impl Write) -> io::Result<Stop> {
+        for i in 0..n {
+            if i > 0 && self.breakpoints.contains(&self.cpu.pc()) {
+                return Ok(Stop::Breakpoint(self.cpu.pc()));
+            }
+            match self.cpu.step_watched() {
+                Ok(Some(Watched::Event(Event::Output(value)))) => writeln!(out, "output: {value}")?,
+                Ok(Some(Watched::Event(Event::NeedsInput))) => return Ok(Stop::NeedsInput),
+                Ok(Some(Watched::Event(Event::Halted))) => return Ok(Stop::Halted),
+                Ok(Some(Watched::Hit(hit))) => return Ok(Stop::Watch(hit)),
+                Ok(None) => (),
+                Err(e) => return Ok(Stop::Error(e)),
+            }
+        }
+        Ok(Stop::Stepped)
+    }
+
+    pub fn cont(&mut self, out: &mut impl Write) -> io::Result<Stop> {
+        self.step(usize::MAX, out)
+    }
+
+    fn list(&self, n: usize, out: &mut impl Write) -> io::Result<()> {
+        let mut addr = self.cpu.pc();
+        for _ in 0..n {
+            let window: Vec<_> = (addr..addr + 4).map(|a| self.cpu.peek(a)).collect();
+            let instruction = Instruction::decode(&window, 0);
+            let marker = match (addr == self.cpu.pc(), self.breakpoints.contains(&addr)) {
+                (true, _) => "=>",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+            writeln!(out, "{marker} {addr:>5}: {instruction}")?;
+            addr += instruction.size();
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(true);
+        };
+        let args: Vec<&str> = words.collect();
+        let arg = |i: usize| args.get(i).and_then(|s| s.parse::<i64>().ok());
+
+        match (command, arg(0), arg(1)) {
+            ("s" | "step", n, _) => {
+                let stop = self.step(n.unwrap_or(1).max(1) as usize, out)?;
+                self.report(stop, out)?;
+            }
+            ("c" | "continue", _, _) => {
+                let stop = self.cont(out)?;
+                self.report(stop, out)?;
+            }
+            ("b" | "break", Some(addr), _) if addr >= 0 => {
+                self.breakpoints.insert(addr as usize);
+                writeln!(out, "breakpoint at {addr}")?;
+            }
+            ("b" | "break", None, _) if args.is_empty() => {
+                for addr in &self.breakpoints {
+                    writeln!(out, "breakpoint at {addr}")?;
+                }
+            }
+            ("d" | "delete", Some(addr), _) if addr >= 0 => {
+                if !self.breakpoints.remove(&(addr as usize)) {
+                    writeln!(out, "no breakpoint at {addr}")?;
+                }
+            }
+            ("w" | "watch", Some(addr), n) if addr >= 0 => match range(addr, n) {
+                Some(range) => {
+                    writeln!(out, "watching writes to {range:?}")?;
+                    self.cpu.watches.push(Watch::Write(range));
+                }
+                None => writeln!(out, "addresses out of range")?,
+            },
+            ("l" | "list", n, _) => self.list(n.unwrap_or(5).max(1) as usize, out)?,
+            ("p" | "peek", Some(addr), n) if addr >= 0 => match range(addr, n) {
+                Some(range) if range.len() <= MAX_PEEK => {
+                    let values: Vec<_> = range.map(|a| self.cpu.peek(a).to_string()).collect();
+                    writeln!(out, "[{addr}] {}", values.join(","))?;
+                }
+                Some(_) => writeln!(out, "can peek at most {MAX_PEEK} words at once")?,
+                None => writeln!(out, "addresses out of range")?,
+            },
+            ("poke", Some(addr), Some(value)) if addr >= 0 => {
+                self.cpu.poke(addr as usize, value);
+            }
+            ("r" | "regs", _, _) => writeln!(
+                out,
+                "pc: {}, rb: {}, pending inputs: {}",
+                self.cpu.pc(),
+                self.cpu.relative_base,
+                self.cpu.pending_inputs()
+            )?,
+            ("i" | "input", _, _) if !args.is_empty() => {
+                let values: Result<Vec<i64>, _> =
+                    args.join("").split(',').map(|s| s.trim().parse()).collect();
+                match values {
+                    Ok(values) => values.into_iter().for_each(|v| self.cpu.provide_input(v)),
+                    Err(_) => writeln!(out, "could not parse input values")?,
+                }
+            }
+            ("o" | "outputs", _, _) => {
+                let outputs: Vec<_> = self.cpu.outputs.iter().map(|v| v.to_string()).collect();
+                writeln!(out, "{}", outputs.join(","))?;
+            }
+            ("q" | "quit", _, _) => return Ok(false),
+            ("h" | "help", _, _) => writeln!(out, "{HELP}")?,
+            _ => writeln!(
+                out,
+                "invalid command {line:?}, type `help` for a list of commands"
+            )?,
+        }
+        Ok(true)
+    }
+
+    fn report(&self, stop: Stop, out: &mut impl Write) -> io::Result<()> {
+        match stop {
+            Stop::Stepped => self.list(1, out),
+            Stop::Breakpoint(addr) => {
+                writeln!(out, "breakpoint hit at {addr}")?;
+                self.list(1, out)
+            }
+            Stop::Watch(hit) => {
+                writeln!(out, "watchpoint: {hit}")?;
+                self.list(1, out)
+            }
+            Stop::NeedsInput => writeln!(
+                out,
+                "waiting for input at {}, queue values with `input`",
+                self.cpu.pc()
+            ),
+            Stop::Halted => writeln!(out, "halted at {}", self.cpu.pc()),
+            Stop::Error(e) => writeln!(out, "error: {e}"),
+        }
+    }
+
+    /// Reads commands from `input` until it is exhausted or `quit` is entered.
+    pub fn repl(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "(intcode) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            if !self.execute(&line?, out)? {
+                break;
+            }
+            write!(out, "(intcode) ")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// The `n` (default 1) addresses starting at `addr`, unless they run past `i64::MAX`.
+fn range(addr: i64, n: Option<i64>) -> Option<Range<usize>> {
+    let end = addr.checked_add(n.unwrap_or(1).max(1))?;
+    Some(addr as usize..end as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+
+    const COUNTDOWN: &str = "        in [n]
+                         loop:  out [n]
+                                add [n], #-1, [n]
+                                jt [n], #loop
+                                halt
+                         n:     data 0";
+
+    /// Runs `commands` in the REPL and returns what each of them printed.
+    fn session(program: Vec<i64>, commands: &[&str]) -> Vec<String> {
+        let mut debugger = Debugger::new(Cpu::new(program));
+        let mut out = Vec::new();
+        let input = commands.join("\n");
+        debugger.repl(input.as_bytes(), &mut out).unwrap();
+        let transcript = String::from_utf8(out).unwrap();
+        let mut responses: Vec<_> = transcript.split("(intcode) ").map(String::from).collect();
+        responses.remove(0);
+        responses
+    }
+
+    #[test]
+    fn test_session() {
+        let responses = session(
+            assemble(COUNTDOWN).unwrap(),
+            &[
+                "input 2",
+                "break 4",
+                "continue",
+                "peek 12",
+                "step",
+                "peek 10 3",
+                "continue",
+                "delete 4",
+                "continue",
+                "outputs",
+                "quit",
+            ],
+        );
+        assert_eq!(
+            responses,
+            vec![
+                "",
+                "breakpoint at 4\n",
+                "output: 2\nbreakpoint hit at 4\n=>     4: add [12], #-1, [12]\n",
+                "[12] 2\n",
+                "=>     8: jt [12], #2\n",
+                "[10] 2,99,1\n",
+                "output: 1\nbreakpoint hit at 4\n=>     4: add [12], #-1, [12]\n",
+                "",
+                "halted at 11\n",
+                "2,1\n",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        let program = vec![1101, 1, 1, 0, 42];
+        let mut debugger = Debugger::new(Cpu::new(program.clone()));
+        assert_eq!(
+            debugger.step(5, &mut Vec::new()).unwrap(),
+            Stop::Error(CpuError::InvalidOpCode { pc: 4, code: 42 })
+        );
+
+        let responses = session(program, &["continue", "peek 0", "quit"]);
+        assert_eq!(
+            responses,
+            vec!["error: invalid opcode 42 at pc 4\n", "[0] 2\n", ""]
+        );
+
+        let huge = i64::MAX.to_string();
+        let responses = session(
+            vec![99],
+            &[
+                &format!("watch 1 {huge}"),
+                &format!("peek 0 {huge}"),
+                &format!("peek {huge} 2"),
+                "watch 1 2",
+                "quit",
+            ],
+        );
+        assert_eq!(
+            responses,
+            vec![
+                "addresses out of range\n",
+                "can peek at most 1000 words at once\n",
+                "addresses out of range\n",
+                "watching writes to 1..3\n",
+                "",
+            ]
+        );
+    }
+}
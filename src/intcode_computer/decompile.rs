@@ -0,0 +1,825 @@
+//! Lifts Intcode into structured pseudo-code.
+//!
+//! Functions are found through the calling convention of compiled puzzle inputs: the caller
+//! writes arguments to `rb+1`, `rb+2`, ... and the return address to `rb+0`, then jumps to the
+//! function. The function moves the relative base past its frame with `rb #n`, restores it with
+//! `rb #-n` and returns through the return address, e.g. `jf #0, rb+0`. Stack slots are numbered
+//! relative to the relative base at function entry, so slot 0 holds the return address and
+//! slots read before they are written are arguments.
+use super::analysis::{pushed_constant, Block, Cfg};
+use super::disasm::{Instruction, Param};
+use super::opcode::OpCode;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Place {
+    Memory(i64),
+    /// Offset from the relative base at function entry.
+    Slot(i64),
+    /// Offset from a relative base that is not known statically.
+    Relative(i64),
+}
+
+impl Place {
+    fn of(param: &Param, delta: Option<i64>) -> Option<Self> {
+        match (param, delta) {
+            (Param::Position(addr), _) => Some(Place::Memory(*addr)),
+            (Param::Immediate(_), _) => None,
+            (Param::Relative(offset), delta) => Some(
+                delta
+                    .and_then(|delta| delta.checked_add(*offset))
+                    .map_or(Place::Relative(*offset), Place::Slot),
+            ),
+        }
+    }
+
+    fn offset(self, n: i64) -> Option<Self> {
+        match self {
+            Place::Memory(addr) => addr.checked_add(n).map(Place::Memory),
+            Place::Slot(slot) => slot.checked_add(n).map(Place::Slot),
+            Place::Relative(offset) => offset.checked_add(n).map(Place::Relative),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cond {
+    lhs: String,
+    op: &'static str,
+    rhs: String,
+}
+
+impl Cond {
+    fn negate(self) -> Self {
+        let op = match self.op {
+            "<" => ">=",
+            ">=" => "<",
+            "==" => "!=",
+            _ => "==",
+        };
+        Cond { op, ..self }
+    }
+}
+
+impl Display for Cond {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+    }
+}
+
+/// How control leaves a block. Conditional exits fall through when the condition is false.
+#[derive(Clone)]
+enum Exit {
+    Fall,
+    Jump { cond: Option<Cond>, target: usize },
+    Indirect { cond: Option<Cond>, target: String },
+    Return { cond: Option<Cond> },
+    Stop,
+}
+
+struct Statement {
+    target: Option<Place>,
+    text: String,
+    value: Option<String>,
+    cond: Option<Cond>,
+}
+
+struct Function {
+    entry: usize,
+    /// Block starts in address order.
+    blocks: Vec<usize>,
+    /// Relative base at the start of each block, relative to the function entry.
+    deltas: HashMap<usize, Option<i64>>,
+    names: BTreeMap<i64, String>,
+    params: i64,
+    /// Reads of each place, except by a jump right after the comparison that wrote it.
+    reads: HashMap<Place, usize>,
+}
+
+impl Function {
+    fn discover(cfg: &Cfg, entry: usize) -> Self {
+        let mut deltas = HashMap::from([(entry, Some(0))]);
+        let mut queue = vec![entry];
+        while let Some(start) = queue.pop() {
+            let Some(block) = cfg.blocks.get(&start) else {
+                continue;
+            };
+            let delta = block_delta(block, deltas[&start]);
+            let successors = block
+                .successors
+                .iter()
+                .filter(|&&s| block.call != Some(s) || s == block.end());
+            for &successor in successors {
+                // paths that disagree on the relative base make it unknown.
+                let merged = match deltas.get(&successor) {
+                    None => delta,
+                    Some(&known) if known.is_some() && known != delta => None,
+                    Some(_) => continue,
+                };
+                deltas.insert(successor, merged);
+                queue.push(successor);
+            }
+        }
+
+        let mut blocks: Vec<_> = deltas
+            .keys()
+            .copied()
+            .filter(|start| cfg.blocks.contains_key(start))
+            .collect();
+        blocks.sort_unstable();
+
+        let mut first_read: BTreeMap<i64, bool> = BTreeMap::new();
+        let mut reads: HashMap<Place, usize> = HashMap::new();
+        for start in &blocks {
+            let mut delta = deltas[start];
+            let mut compared = None;
+            for instruction in &cfg.blocks[start].instructions {
+                let Instruction::Op { opcode, params, .. } = instruction else {
+                    continue;
+                };
+                for (i, param) in params[..opcode.reads()].iter().enumerate() {
+                    let Some(place) = Place::of(param, delta) else {
+                        continue;
+                    };
+                    let condition = matches!(opcode, OpCode::Jt | OpCode::Jf) && i == 0;
+                    if !(condition && compared == Some(place)) {
+                        *reads.entry(place).or_default() += 1;
+                    }
+                    if let Place::Slot(slot) = place {
+                        first_read.entry(slot).or_insert(true);
+                    }
+                }
+                let target = params
+                    .last()
+                    .filter(|_| opcode.writes())
+                    .and_then(|param| Place::of(param, delta));
+                if let Some(Place::Slot(slot)) = target {
+                    first_read.entry(slot).or_insert(false);
+                }
+                compared = target.filter(|_| matches!(opcode, OpCode::Lt | OpCode::Eq));
+                delta = instruction_delta(instruction, delta);
+            }
+        }
+
+        let names = first_read
+            .iter()
+            .map(|(&slot, &read)| {
+                let name = match slot {
+                    0 => "ret".to_string(),
+                    _ if slot < 0 => format!("up{}", slot.unsigned_abs()),
+                    _ if read && entry != 0 => format!("arg{slot}"),
+                    _ => format!("var{slot}"),
+                };
+                (slot, name)
+            })
+            .collect();
+        let params = first_read
+            .iter()
+            .filter(|(&slot, &read)| slot > 0 && read && entry != 0)
+            .map(|(&slot, _)| slot)
+            .max()
+            .unwrap_or(0);
+
+        Function {
+            entry,
+            blocks,
+            deltas,
+            names,
+            params,
+            reads,
+        }
+    }
+
+    fn name(&self) -> String {
+        function_name(self.entry)
+    }
+
+    fn place(&self, place: Place) -> String {
+        match place {
+            Place::Memory(addr) => format!("mem[{addr}]"),
+            Place::Slot(slot) => self
+                .names
+                .get(&slot)
+                .cloned()
+                .unwrap_or_else(|| format!("slot{slot}")),
+            Place::Relative(offset) => format!("rb[{offset}]"),
+        }
+    }
+
+    fn operand(&self, param: &Param, delta: Option<i64>) -> String {
+        match Place::of(param, delta) {
+            Some(place) => self.place(place),
+            None => param.value().to_string(),
+        }
+    }
+}
+
+fn function_name(entry: usize) -> String {
+    match entry {
+        0 => "main".to_string(),
+        _ => format!("f_{entry}"),
+    }
+}
+
+fn instruction_delta(instruction: &Instruction, delta: Option<i64>) -> Option<i64> {
+    match instruction {
+        Instruction::Op {
+            opcode: OpCode::Rb,
+            params,
+            ..
+        } => match params[0] {
+            Param::Immediate(n) => delta.and_then(|delta| delta.checked_add(n)),
+            _ => None,
+        },
+        _ => delta,
+    }
+}
+
+fn block_delta(block: &Block, delta: Option<i64>) -> Option<i64> {
+    block.instructions.iter().fold(delta, |delta, instruction| {
+        instruction_delta(instruction, delta)
+    })
+}
+
+fn expression(opcode: OpCode, params: &[Param], a: String, b: String) -> (String, Option<Cond>) {
+    let cond = |op| Cond {
+        lhs: a.clone(),
+        op,
+        rhs: b.clone(),
+    };
+    let value = match (opcode, params[0], params[1]) {
+        (OpCode::Add, _, Param::Immediate(0)) => a,
+        (OpCode::Add, Param::Immediate(0), _) => b,
+        (OpCode::Add, _, Param::Immediate(n)) if n < 0 => format!("{a} - {}", -(n as i128)),
+        (OpCode::Add, _, _) => format!("{a} + {b}"),
+        (OpCode::Mul, _, Param::Immediate(1)) => a,
+        (OpCode::Mul, Param::Immediate(1), _) => b,
+        (OpCode::Mul, _, Param::Immediate(-1)) => format!("-{a}"),
+        (OpCode::Mul, Param::Immediate(-1), _) => format!("-{b}"),
+        (OpCode::Mul, _, _) => format!("{a} * {b}"),
+        (OpCode::Lt, _, _) => return (cond("<").to_string(), Some(cond("<"))),
+        _ => return (cond("==").to_string(), Some(cond("=="))),
+    };
+    (value, None)
+}
+
+enum Node {
+    Block(usize),
+    Exit(usize),
+    If {
+        cond: Cond,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Loop {
+        range: Loop,
+        body: Vec<Node>,
+        cond: Option<Cond>,
+    },
+}
+
+/// A loop being structured: the index of its head and of the block after it.
+#[derive(Clone, Copy)]
+struct Loop {
+    head: usize,
+    exit: usize,
+}
+
+struct Decompiler<'a> {
+    cfg: &'a Cfg,
+    functions: BTreeMap<usize, Function>,
+}
+
+impl Decompiler<'_> {
+    fn lift(&self, function: &Function, block: &Block) -> (Vec<String>, Exit) {
+        let mut delta = function.deltas[&block.start];
+        let mut statements: Vec<Statement> = Vec::new();
+        let mut exit = Exit::Fall;
+        let mut return_slot = None;
+
+        for instruction in &block.instructions {
+            let before = delta;
+            delta = instruction_delta(instruction, delta);
+            let Instruction::Op { opcode, params, .. } = instruction else {
+                statements.push(Statement {
+                    target: None,
+                    text: format!("invalid({});", instruction_value(instruction)),
+                    value: None,
+                    cond: None,
+                });
+                exit = Exit::Stop;
+                break;
+            };
+
+            let operand = |i: usize| function.operand(&params[i], before);
+            let target = || Place::of(params.last().unwrap(), before);
+            match opcode {
+                OpCode::Add | OpCode::Mul | OpCode::Lt | OpCode::Eq => {
+                    if block.call.is_some()
+                        && pushed_constant(instruction) == Some(block.end() as i64)
+                    {
+                        return_slot = target();
+                        continue;
+                    }
+                    let (value, cond) = expression(*opcode, params, operand(0), operand(1));
+                    statements.push(Statement {
+                        target: target(),
+                        text: format!("{} = {value};", operand(2)),
+                        value: Some(value),
+                        cond,
+                    });
+                }
+                OpCode::In => statements.push(Statement {
+                    target: target(),
+                    text: format!("{} = input();", operand(0)),
+                    value: None,
+                    cond: None,
+                }),
+                OpCode::Out => statements.push(Statement {
+                    target: None,
+                    text: format!("output({});", operand(0)),
+                    value: None,
+                    cond: None,
+                }),
+                OpCode::Rb if before.is_some() && delta.is_some() => (),
+                OpCode::Rb => statements.push(Statement {
+                    target: None,
+                    text: format!("rb += {};", operand(0)),
+                    value: None,
+                    cond: None,
+                }),
+                OpCode::Halt => {
+                    statements.push(Statement {
+                        target: None,
+                        text: "halt();".to_string(),
+                        value: None,
+                        cond: None,
+                    });
+                    exit = Exit::Stop;
+                }
+                OpCode::Jt | OpCode::Jf if block.call.is_some() => (),
+                OpCode::Jt | OpCode::Jf => {
+                    let cond = match params[0] {
+                        Param::Immediate(c) if (c != 0) == (*opcode == OpCode::Jt) => None,
+                        Param::Immediate(_) => continue,
+                        param => {
+                            let place = Place::of(&param, before);
+                            let inlined = statements.last().is_some_and(|last| {
+                                last.cond.is_some()
+                                    && last.target == place
+                                    && place.is_some_and(|p| !function.reads.contains_key(&p))
+                            });
+                            let cond = match inlined {
+                                true => statements.pop().unwrap().cond.unwrap(),
+                                false => Cond {
+                                    lhs: operand(0),
+                                    op: "!=",
+                                    rhs: "0".to_string(),
+                                },
+                            };
+                            Some(match opcode {
+                                OpCode::Jt => cond,
+                                _ => cond.negate(),
+                            })
+                        }
+                    };
+                    exit = match params[1] {
+                        Param::Immediate(target) if target >= 0 => Exit::Jump {
+                            cond,
+                            target: target as usize,
+                        },
+                        param if Place::of(&param, before) == Some(Place::Slot(0)) => {
+                            Exit::Return { cond }
+                        }
+                        _ => Exit::Indirect {
+                            cond,
+                            target: operand(1),
+                        },
+                    };
+                }
+            }
+        }
+
+        if let (Some(callee), Some(slot)) = (block.call, return_slot) {
+            let params = self.functions.get(&callee).map_or(0, |f| f.params);
+            let args: Vec<_> = (1..=params)
+                .map(|n| {
+                    let place = slot.offset(n).unwrap_or(Place::Relative(n));
+                    match statements.iter().rposition(|s| s.target == Some(place)) {
+                        Some(i) => statements.remove(i).value.unwrap_or_default(),
+                        None => function.place(place),
+                    }
+                })
+                .collect();
+            statements.push(Statement {
+                target: None,
+                text: format!("{}({});", function_name(callee), args.join(", ")),
+                value: None,
+                cond: None,
+            });
+        }
+
+        (statements.into_iter().map(|s| s.text).collect(), exit)
+    }
+
+    fn structure(
+        &self,
+        exits: &[Exit],
+        starts: &[usize],
+        (lo, hi): (usize, usize),
+        loops: &[Loop],
+        consumed: &BTreeSet<usize>,
+    ) -> Vec<Node> {
+        let index = |addr: usize| starts.binary_search(&addr).ok();
+        // a jump to the end of a loop body is a `break`, not the end of an `if`.
+        let limit = match loops.last() {
+            Some(l) if l.exit == hi => hi - 1,
+            _ => hi,
+        };
+
+        let mut nodes = Vec::new();
+        let mut i = lo;
+        while i < hi {
+            let back_edge = (i..hi).rev().find(|&j| {
+                !consumed.contains(&j)
+                    && matches!(exits[j], Exit::Jump { target, .. } if index(target) == Some(i))
+            });
+            if let (Some(j), false) = (back_edge, loops.iter().any(|l| l.head == i)) {
+                let Exit::Jump { cond, .. } = &exits[j] else {
+                    unreachable!()
+                };
+                let range = Loop {
+                    head: i,
+                    exit: j + 1,
+                };
+                let inner = [loops, &[range]].concat();
+                let mut consumed = consumed.clone();
+                consumed.insert(j);
+                nodes.push(Node::Loop {
+                    range,
+                    body: self.structure(exits, starts, (i, j + 1), &inner, &consumed),
+                    cond: cond.clone(),
+                });
+                i = j + 1;
+                continue;
+            }
+
+            nodes.push(Node::Block(i));
+            if consumed.contains(&i) {
+                i += 1;
+                continue;
+            }
+            match &exits[i] {
+                Exit::Jump {
+                    cond: Some(cond),
+                    target,
+                } if index(*target).is_some_and(|t| t > i + 1 && t <= limit) => {
+                    let t = index(*target).unwrap();
+                    let join = match &exits[t - 1] {
+                        Exit::Jump { cond: None, target } => {
+                            index(*target).filter(|&u| u > t && u <= limit)
+                        }
+                        _ => None,
+                    };
+                    let mut inner = consumed.clone();
+                    match join {
+                        Some(u) => {
+                            inner.insert(t - 1);
+                            nodes.push(Node::If {
+                                cond: cond.clone().negate(),
+                                then: self.structure(exits, starts, (i + 1, t), loops, &inner),
+                                otherwise: self.structure(exits, starts, (t, u), loops, consumed),
+                            });
+                            i = u;
+                        }
+                        None => {
+                            nodes.push(Node::If {
+                                cond: cond.clone().negate(),
+                                then: self.structure(exits, starts, (i + 1, t), loops, consumed),
+                                otherwise: Vec::new(),
+                            });
+                            i = t;
+                        }
+                    }
+                }
+                Exit::Fall => i += 1,
+                Exit::Jump { target, .. } if index(*target) == Some(i + 1) => i += 1,
+                _ => {
+                    nodes.push(Node::Exit(i));
+                    i += 1;
+                }
+            }
+        }
+        nodes
+    }
+
+    fn function(&self, function: &Function, out: &mut String) {
+        let blocks: Vec<_> = function
+            .blocks
+            .iter()
+            .map(|start| self.lift(function, &self.cfg.blocks[start]))
+            .collect();
+        let exits: Vec<_> = blocks.iter().map(|(_, exit)| exit.clone()).collect();
+        let nodes = self.structure(
+            &exits,
+            &function.blocks,
+            (0, function.blocks.len()),
+            &[],
+            &BTreeSet::new(),
+        );
+
+        let mut listing = Listing {
+            blocks,
+            starts: &function.blocks,
+            labels: BTreeSet::new(),
+        };
+        listing.collect_labels(&nodes, &[]);
+
+        let params: Vec<_> = (1..=function.params)
+            .map(|slot| function.place(Place::Slot(slot)))
+            .collect();
+        writeln!(out, "fn {}({}) {{", function.name(), params.join(", ")).unwrap();
+        listing.render(&nodes, &[], 1, out);
+        writeln!(out, "}}").unwrap();
+    }
+}
+
+fn instruction_value(instruction: &Instruction) -> i64 {
+    match instruction {
+        Instruction::Data { value, .. } => *value,
+        Instruction::Op { opcode, .. } => opcode.code(),
+    }
+}
+
+/// The lifted blocks of a function, rendered along its structure.
+struct Listing<'a> {
+    blocks: Vec<(Vec<String>, Exit)>,
+    starts: &'a [usize],
+    /// Jump targets rendered as `goto`.
+    labels: BTreeSet<usize>,
+}
+
+impl Listing<'_> {
+    /// A jump to `target` as `break`, `continue` or `goto`.
+    fn jump(&self, target: usize, loops: &[Loop]) -> String {
+        let index = self.starts.binary_search(&target).ok();
+        match loops.last() {
+            Some(l) if index == Some(l.exit) => "break;".to_string(),
+            Some(l) if index == Some(l.head) => "continue;".to_string(),
+            _ => format!("goto L{target};"),
+        }
+    }
+
+    fn collect_labels(&mut self, nodes: &[Node], loops: &[Loop]) {
+        for node in nodes {
+            match node {
+                Node::Exit(i) => {
+                    if let Exit::Jump { target, .. } = self.blocks[*i].1 {
+                        if self.jump(target, loops).starts_with("goto") {
+                            self.labels.insert(target);
+                        }
+                    }
+                }
+                Node::If {
+                    then, otherwise, ..
+                } => {
+                    self.collect_labels(then, loops);
+                    self.collect_labels(otherwise, loops);
+                }
+                Node::Loop { range, body, .. } => {
+                    self.collect_labels(body, &[loops, &[*range]].concat());
+                }
+                Node::Block(_) => (),
+            }
+        }
+    }
+
+    fn render(&self, nodes: &[Node], loops: &[Loop], depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        for node in nodes {
+            match node {
+                Node::Block(i) => {
+                    let start = self.starts[*i];
+                    if self.labels.contains(&start) {
+                        writeln!(out, "{}L{start}:", "    ".repeat(depth - 1)).unwrap();
+                    }
+                    for statement in &self.blocks[*i].0 {
+                        writeln!(out, "{indent}{statement}").unwrap();
+                    }
+                }
+                Node::Exit(i) => {
+                    let (cond, statement) = match &self.blocks[*i].1 {
+                        Exit::Jump { cond, target } => (cond, self.jump(*target, loops)),
+                        Exit::Indirect { cond, target } => (cond, format!("goto *{target};")),
+                        Exit::Return { cond } => (cond, "return;".to_string()),
+                        Exit::Fall | Exit::Stop => continue,
+                    };
+                    match cond {
+                        Some(cond) => writeln!(out, "{indent}if ({cond}) {statement}").unwrap(),
+                        None => writeln!(out, "{indent}{statement}").unwrap(),
+                    }
+                }
+                Node::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    writeln!(out, "{indent}if ({cond}) {{").unwrap();
+                    self.render(then, loops, depth + 1, out);
+                    if !otherwise.is_empty() {
+                        writeln!(out, "{indent}}} else {{").unwrap();
+                        self.render(otherwise, loops, depth + 1, out);
+                    }
+                    writeln!(out, "{indent}}}").unwrap();
+                }
+                Node::Loop { range, body, cond } => {
+                    let keyword = if cond.is_some() { "do" } else { "loop" };
+                    writeln!(out, "{indent}{keyword} {{").unwrap();
+                    self.render(body, &[loops, &[*range]].concat(), depth + 1, out);
+                    match cond {
+                        Some(cond) => writeln!(out, "{indent}}} while ({cond});").unwrap(),
+                        None => writeln!(out, "{indent}}}").unwrap(),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Decompiles every function reachable from address 0 into pseudo-code.
+pub fn decompile(program: &[i64]) -> String {
+    let cfg = Cfg::build(program);
+    let entries: BTreeSet<usize> = std::iter::once(0)
+        .chain(cfg.blocks.values().filter_map(|block| block.call))
+        .collect();
+
+    let decompiler = Decompiler {
+        functions: entries
+            .iter()
+            .map(|&entry| (entry, Function::discover(&cfg, entry)))
+            .collect(),
+        cfg: &cfg,
+    };
+
+    let mut out = String::new();
+    for (i, function) in decompiler.functions.values().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        decompiler.function(function, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{asm::assemble, cpu::Cpu, fuzz};
+
+    #[test]
+    fn test_decompile() {
+        let program = assemble(
+            "        rb #100
+                     in rb+1
+             loop:   rb #2
+                     add rb-1, #0, rb+1
+                     add #back, #0, rb+0
+                     jt #1, #square
+             back:   rb #-2
+                     out rb+3
+                     add rb+1, #-1, rb+1
+                     jt rb+1, #loop
+                     halt
+             square: mul rb+1, rb+1, rb+2
+                     add rb+2, #0, rb+1
+                     jf #0, rb+0",
+        )
+        .unwrap();
+
+        let mut cpu = Cpu::new(program.clone());
+        cpu.run(Some(vec![3])).unwrap();
+        assert_eq!(cpu.outputs, vec![9, 4, 1]);
+
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    var101 = input();
+    do {
+        f_29(var101);
+        output(var103);
+        var101 = var101 - 1;
+    } while (var101 != 0);
+    halt();
+}
+
+fn f_29(arg1) {
+    var2 = arg1 * arg1;
+    arg1 = var2;
+    return;
+}
+"
+        );
+
+        let program = assemble(
+            "        in [x]
+                     lt [x], #0, [t]
+                     jf [t], #else
+                     mul [x], #-1, [x]
+                     jt #1, #end
+             else:   add [x], #1, [x]
+             end:    out [x]
+                     halt
+             x:      data 0
+             t:      data 0",
+        )
+        .unwrap();
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    mem[23] = input();
+    if (mem[23] < 0) {
+        mem[23] = -mem[23];
+    } else {
+        mem[23] = mem[23] + 1;
+    }
+    output(mem[23]);
+    halt();
+}
+"
+        );
+
+        let program = assemble(
+            "        in [n]
+             loop:   eq [n], #0, [t]
+                     jt [t], #done
+                     lt [n], #5, [t]
+                     jt [t], #skip
+                     out [n]
+             skip:   add [n], #-1, [n]
+                     jt #1, #loop
+             done:   out #0
+                     halt
+             n:      data 0
+             t:      data 0",
+        )
+        .unwrap();
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    mem[28] = input();
+    loop {
+        if (mem[28] == 0) break;
+        if (mem[28] >= 5) {
+            output(mem[28]);
+        }
+        mem[28] = mem[28] - 1;
+    }
+    output(0);
+    halt();
+}
+"
+        );
+
+        // irregular control flow falls back to gotos instead of failing.
+        for seed in 0..200 {
+            decompile(&fuzz::generate(seed, 30));
+        }
+    }
+
+    #[test]
+    fn test_extreme_relative_base() {
+        // the relative base leaves the i64 range, so its offsets cannot be tracked statically.
+        let program = assemble(&format!(
+            "rb #{}
+             out rb+1
+             rb #1
+             out rb+0
+             halt",
+            i64::MAX
+        ))
+        .unwrap();
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    output(rb[1]);
+    rb += 1;
+    output(rb[0]);
+    halt();
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lowest_slot() {
+        assert_eq!(
+            decompile(&[204, i64::MIN, 31, 104]),
+            "fn main() {
+    output(up9223372036854775808);
+    invalid(31);
+}
+"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use super::opcode::OpCode;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Param {
+    fn decode(mode: i64, value: i64) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Position(value)),
+            1 => Some(Self::Immediate(value)),
+            2 => Some(Self::Relative(value)),
+            _ => None,
+        }
+    }
+
+    pub fn mode(&self) -> i64 {
+        match self {
+            Param::Position(_) => 0,
+            Param::Immediate(_) => 1,
+            Param::Relative(_) => 2,
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        match self {
+            Param::Position(v) | Param::Immediate(v) | Param::Relative(v) => *v,
+        }
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Param::Position(v) => write!(f, "[{v}]"),
+            Param::Immediate(v) => write!(f, "#{v}"),
+            Param::Relative(v) if *v < 0 => write!(f, "rb{v}"),
+            Param::Relative(v) => write!(f, "rb+{v}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Op {
+        addr: usize,
+        opcode: OpCode,
+        params: Vec<Param>,
+    },
+    Data {
+        addr: usize,
+        value: i64,
+    },
+}
+
+impl Instruction {
+    pub fn addr(&self) -> usize {
+        match self {
+            Instruction::Op { addr, .. } | Instruction::Data { addr, .. } => *addr,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::Op { params, .. } => 1 + params.len(),
+            Instruction::Data { .. } => 1,
+        }
+    }
+
+    /// Decodes the instruction at `addr`. Words that are not a canonical encoding of an
+    /// instruction (unknown opcode, invalid or superfluous mode digits, or parameters running
+    /// past the end of the program) are returned as data.
+    pub fn decode(program: &[i64], addr: usize) -> Self {
+        let value = program[addr];
+        let data = Instruction::Data { addr, value };
+
+        let Ok(opcode) = OpCode::try_from(value) else {
+            return data;
+        };
+        let n = opcode.params();
+        if addr + n >= program.len() || value / 10_i64.pow(2 + n as u32) != 0 {
+            return data;
+        }
+
+        let params: Option<Vec<_>> = (0..n)
+            .map(|i| {
+                let mode = (value / 10_i64.pow(2 + i as u32)) % 10;
+                Param::decode(mode, program[addr + 1 + i])
+            })
+            .collect();
+
+        match params {
+            Some(params) => Instruction::Op {
+                addr,
+                opcode,
+                params,
+            },
+            None => data,
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Op { opcode, params, .. } if params.is_empty() => write!(f, "{opcode}"),
+            Instruction::Op { opcode, params, .. } => {
+                let params: Vec<_> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "{opcode} {}", params.join(", "))
+            }
+            Instruction::Data { value, .. } => write!(f, "data {value}"),
+        }
+    }
+}
+
+pub fn disassemble(program: &[i64]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        let instruction = Instruction::decode(program, addr);
+        addr += instruction.size();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Renders a listing with one instruction per line: address, raw words and the decoded
+/// instruction.
+pub fn listing(program: &[i64]) -> String {
+    disassemble(program)
+        .into_iter()
+        .map(|instruction| {
+            let addr = instruction.addr();
+            let raw: Vec<_> = program[addr..addr + instruction.size()]
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            format!("{addr:>5}: {:<28} {instruction}\n", raw.join(","))
+        })
+        .collect()
+}
+
+/// Renders the program as source for [`assemble`](super::asm::assemble), one instruction per line.
+pub fn source(program: &[i64]) -> String {
+    disassemble(program)
+        .into_iter()
+        .map(|instruction| format!("{instruction}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing() {
+        // 42 is not an opcode and the final `rb` is missing its parameter.
+        let program = [1001, 123, 5, 123, 22201, 4, -1, 4, 42, 99, 109];
+        assert_eq!(
+            listing(&program),
+            "    0: 1001,123,5,123               add [123], #5, [123]
+    4: 22201,4,-1,4                 add rb+4, rb-1, rb+4
+    8: 42                           data 42
+    9: 99                           halt
+   10: 109                          data 109
+"
+        );
+    }
+}
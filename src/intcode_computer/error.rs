@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    InvalidOpCode { pc: usize, code: i64 },
+    InvalidMode { pc: usize, mode: u8 },
+    WriteToImmediate { pc: usize },
+    NegativeAddress { pc: usize, addr: i64 },
+    InputExhausted { pc: usize },
+    InvalidInput { pc: usize, input: String },
+    ChannelClosed { pc: usize },
+    StepLimit { pc: usize, steps: u64 },
+    InfiniteLoop { pc: usize },
+    Overflow { pc: usize },
+}
+
+impl CpuError {
+    pub fn pc(&self) -> usize {
+        match self {
+            CpuError::InvalidOpCode { pc, .. }
+            | CpuError::InvalidMode { pc, .. }
+            | CpuError::WriteToImmediate { pc }
+            | CpuError::NegativeAddress { pc, .. }
+            | CpuError::InputExhausted { pc }
+            | CpuError::InvalidInput { pc, .. }
+            | CpuError::ChannelClosed { pc }
+            | CpuError::StepLimit { pc, .. }
+            | CpuError::InfiniteLoop { pc }
+            | CpuError::Overflow { pc } => *pc,
+        }
+    }
+}
+
+impl Display for CpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuError::InvalidOpCode { pc, code } => {
+                write!(f, "invalid opcode {code} at pc {pc}")
+            }
+            CpuError::InvalidMode { pc, mode } => {
+                write!(f, "invalid parameter mode {mode} at pc {pc}")
+            }
+            CpuError::WriteToImmediate { pc } => {
+                write!(f, "write to immediate parameter at pc {pc}")
+            }
+            CpuError::NegativeAddress { pc, addr } => {
+                write!(f, "access to negative address {addr} at pc {pc}")
+            }
+            CpuError::InputExhausted { pc } => write!(f, "input exhausted at pc {pc}"),
+            CpuError::InvalidInput { pc, input } => {
+                write!(f, "invalid device value {input:?} at pc {pc}")
+            }
+            CpuError::ChannelClosed { pc } => write!(f, "channel closed at pc {pc}"),
+            CpuError::StepLimit { pc, steps } => {
+                write!(f, "step limit of {steps} reached at pc {pc}")
+            }
+            CpuError::InfiniteLoop { pc } => {
+                write!(f, "infinite loop detected at pc {pc}")
+            }
+            CpuError::Overflow { pc } => write!(f, "arithmetic overflow at pc {pc}"),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
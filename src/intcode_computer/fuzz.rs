@@ -0,0 +1,258 @@
+//! Differential testing of the independent ways to execute a program.
+use super::asm::{assemble, AsmError};
+use super::cpu::{Cpu, Engine, Msg};
+use super::disasm;
+use super::error::CpuError;
+use super::memory::{FlatMemory, Memory, SparseMemory};
+use super::opcode::OpCode;
+use std::fmt::Display;
+use tokio::sync::mpsc;
+
+/// Keeps generated programs with infinite loops cheap to run.
+const MAX_STEPS: u64 = 2_000;
+/// Words after the code that generated programs use as scratch memory.
+const SCRATCH: usize = 16;
+
+/// A xorshift generator, so every failure can be reproduced from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        min + (self.next() % (max - min + 1) as u64) as i64
+    }
+}
+
+/// Generates a program of `len` random instructions followed by a `halt` and scratch memory.
+/// Operands mostly point into the program, and jump targets mostly at instruction boundaries.
+pub fn generate(seed: u64, len: usize) -> Vec<i64> {
+    let mut rng = Rng::new(seed);
+    let mut instructions: Vec<(OpCode, Vec<(i64, i64)>)> = Vec::new();
+    for _ in 0..len {
+        let opcode = OpCode::ALL[rng.below(OpCode::ALL.len())];
+        let params = (0..opcode.params())
+            .map(|i| {
+                let write = opcode.writes() && i == opcode.params() - 1;
+                match rng.below(if write { 2 } else { 3 }) {
+                    0 => (0, -1),
+                    1 => (2, rng.range(-1, 8)),
+                    _ => (1, rng.range(-3, 20)),
+                }
+            })
+            .collect();
+        instructions.push((opcode, params));
+    }
+
+    let mut starts = vec![0];
+    for (opcode, _) in &instructions {
+        starts.push(starts.last().unwrap() + 1 + opcode.params());
+    }
+    let size = *starts.last().unwrap() + 1 + SCRATCH;
+
+    let mut program = Vec::with_capacity(size);
+    for (opcode, params) in instructions {
+        let modes: i64 = params
+            .iter()
+            .enumerate()
+            .map(|(i, (mode, _))| mode * 10_i64.pow(2 + i as u32))
+            .sum();
+        program.push(opcode.code() + modes);
+        for (i, (mode, value)) in params.into_iter().enumerate() {
+            let jump_target = matches!(opcode, OpCode::Jt | OpCode::Jf) && i == 1 && mode == 1;
+            program.push(match mode {
+                0 => rng.below(size) as i64,
+                _ if jump_target && rng.below(4) > 0 => starts[rng.below(starts.len())] as i64,
+                _ if opcode == OpCode::Rb => value.clamp(-1, 3),
+                _ => value,
+            });
+        }
+    }
+    program.push(OpCode::Halt.code());
+    program.resize(size, 0);
+    program
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub result: Result<(), CpuError>,
+    pub outputs: Vec<i64>,
+    /// Non-zero memory words after the run.
+    pub memory: Vec<(usize, i64)>,
+}
+
+fn run<M: Memory>(program: &[i64], inputs: &[i64], engine: Engine) -> Outcome {
+    let mut cpu: Cpu<M> = Cpu::with_memory(program.to_vec());
+    cpu.max_steps = Some(MAX_STEPS);
+    cpu.set_engine(engine);
+    let result = cpu.run(Some(inputs.to_vec()));
+    outcome(result, cpu.outputs, &cpu.memory)
+}
+
+fn run_async(program: &[i64], inputs: &[i64]) -> Outcome {
+    let mut cpu = Cpu::new(program.to_vec());
+    cpu.max_steps = Some(MAX_STEPS);
+    let (input, mut input_rx) = mpsc::channel(32);
+    let (tx, mut rx) = mpsc::channel(32);
+
+    let driver = async move {
+        let mut input = Some(input);
+        let mut inputs = inputs.iter();
+        let mut outputs = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                Msg::Value(value) => outputs.push(value),
+                Msg::RxRequest => match (inputs.next(), input.as_ref()) {
+                    (Some(&value), Some(tx)) => tx.send(Msg::Value(value)).await.unwrap(),
+                    // closing the channel is how running out of inputs looks to an async cpu.
+                    _ => input = None,
+                },
+            }
+        }
+        outputs
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (result, outputs) =
+        runtime.block_on(async { tokio::join!(cpu.run_async(tx, &mut input_rx), driver) });
+    let result = result.map_err(|e| match e {
+        CpuError::ChannelClosed { pc } => CpuError::InputExhausted { pc },
+        e => e,
+    });
+    outcome(result, outputs, &cpu.memory)
+}
+
+fn outcome(result: Result<(), CpuError>, outputs: Vec<i64>, memory: &impl Memory) -> Outcome {
+    Outcome {
+        result,
+        outputs,
+        memory: memory
+            .words()
+            .into_iter()
+            .filter(|&(_, v)| v != 0)
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// `Cpu::run` against `Cpu::run_async`.
+    Async,
+    /// `FlatMemory` against `SparseMemory`.
+    Memory,
+    /// The program against its disassembled and reassembled source.
+    RoundTrip,
+    /// [`Engine::Interpreter`] against [`Engine::Predecoded`].
+    Engine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The execution path produced a different outcome than `Cpu::run`.
+    Diverged { expected: Outcome, actual: Outcome },
+    /// The disassembled source of the program could not be assembled again.
+    Assemble(AsmError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub check: Check,
+    pub program: Vec<i64>,
+    pub inputs: Vec<i64>,
+    pub failure: Failure,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:?} check failed", self.check)?;
+        writeln!(f, "program: {:?}", self.program)?;
+        writeln!(f, "inputs: {:?}", self.inputs)?;
+        match &self.failure {
+            Failure::Diverged { expected, actual } => {
+                writeln!(f, "expected: {expected:?}")?;
+                write!(f, "actual: {actual:?}")
+            }
+            Failure::Assemble(e) => write!(f, "could not reassemble: {e}"),
+        }
+    }
+}
+
+/// Runs `program` through every execution path and compares the outcomes.
+pub fn check(program: &[i64], inputs: &[i64]) -> Result<(), Box<Mismatch>> {
+    let expected = run::<FlatMemory>(program, inputs, Engine::Interpreter);
+    let mismatch = |check, failure| Mismatch {
+        check,
+        program: program.to_vec(),
+        inputs: inputs.to_vec(),
+        failure,
+    };
+    let diverged = |actual| Failure::Diverged {
+        expected: expected.clone(),
+        actual,
+    };
+
+    let actual = run_async(program, inputs);
+    if actual != expected {
+        return Err(Box::new(mismatch(Check::Async, diverged(actual))));
+    }
+
+    let actual = run::<SparseMemory>(program, inputs, Engine::Interpreter);
+    if actual != expected {
+        return Err(Box::new(mismatch(Check::Memory, diverged(actual))));
+    }
+
+    let reassembled = assemble(&disasm::source(program))
+        .map_err(|e| Box::new(mismatch(Check::RoundTrip, Failure::Assemble(e))))?;
+    let actual = run::<FlatMemory>(&reassembled, inputs, Engine::Interpreter);
+    if actual != expected {
+        return Err(Box::new(mismatch(Check::RoundTrip, diverged(actual))));
+    }
+
+    let actual = run::<FlatMemory>(program, inputs, Engine::Predecoded);
+    if actual != expected {
+        return Err(Box::new(mismatch(Check::Engine, diverged(actual))));
+    }
+    Ok(())
+}
+
+/// Checks `iterations` programs generated from consecutive seeds starting at `seed`.
+pub fn fuzz(seed: u64, iterations: u64) -> Result<(), Box<Mismatch>> {
+    for seed in seed..seed + iterations {
+        let mut rng = Rng::new(seed);
+        let len = 1 + rng.below(24);
+        let inputs: Vec<_> = (0..rng.below(8)).map(|_| rng.range(-5, 20)).collect();
+        check(&generate(seed, len), &inputs)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz() {
+        let program = generate(7, 20);
+        assert_eq!(program, generate(7, 20));
+        assert_eq!(program[program.len() - 17], 99);
+
+        if let Err(mismatch) = fuzz(0, 300) {
+            panic!("{mismatch}");
+        }
+    }
+}
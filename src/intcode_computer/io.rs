@@ -0,0 +1,266 @@
+use super::error::CpuError;
+use std::collections::VecDeque;
+use std::io::stdin;
+use std::sync::mpsc::{Receiver, Sender};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceError {
+    Exhausted,
+    Invalid(String),
+    Closed,
+}
+
+impl DeviceError {
+    /// Attaches the address of the faulting `In` or `Out` instruction.
+    pub fn at(self, pc: usize) -> CpuError {
+        match self {
+            DeviceError::Exhausted => CpuError::InputExhausted { pc },
+            DeviceError::Invalid(input) => CpuError::InvalidInput { pc, input },
+            DeviceError::Closed => CpuError::ChannelClosed { pc },
+        }
+    }
+}
+
+/// A source of values for the `In` instruction.
+pub trait IntcodeInput {
+    fn read(&mut self) -> Result<i64, DeviceError>;
+}
+
+/// A sink for values of the `Out` instruction.
+pub trait IntcodeOutput {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError>;
+}
+
+impl<T: IntcodeInput + ?Sized> IntcodeInput for &mut T {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        (**self).read()
+    }
+}
+
+impl<T: IntcodeOutput + ?Sized> IntcodeOutput for &mut T {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        (**self).write(value)
+    }
+}
+
+/// Pairs a separate input and output into a single device.
+impl<I: IntcodeInput, O> IntcodeInput for (I, O) {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        self.0.read()
+    }
+}
+
+impl<I, O: IntcodeOutput> IntcodeOutput for (I, O) {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        self.1.write(value)
+    }
+}
+
+impl IntcodeOutput for Vec<i64> {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+impl IntcodeInput for VecDeque<i64> {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        self.pop_front().ok_or(DeviceError::Exhausted)
+    }
+}
+
+impl IntcodeOutput for VecDeque<i64> {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+/// Reads from an iterator until it is exhausted.
+pub struct IterInput<T>(pub T);
+
+impl<T: Iterator<Item = i64>> IntcodeInput for IterInput<T> {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        self.0.next().ok_or(DeviceError::Exhausted)
+    }
+}
+
+/// Calls a closure for every value read.
+pub struct FnInput<F>(pub F);
+
+impl<F: FnMut() -> i64> IntcodeInput for FnInput<F> {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        Ok((self.0)())
+    }
+}
+
+/// Calls a closure for every value written.
+pub struct FnOutput<F>(pub F);
+
+impl<F: FnMut(i64)> IntcodeOutput for FnOutput<F> {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+/// Blocks on a channel, e.g. to connect machines running on separate threads.
+pub struct ChannelInput(pub Receiver<i64>);
+
+impl IntcodeInput for ChannelInput {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        self.0.recv().or(Err(DeviceError::Closed))
+    }
+}
+
+pub struct ChannelOutput(pub Sender<i64>);
+
+impl IntcodeOutput for ChannelOutput {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        self.0.send(value).or(Err(DeviceError::Closed))
+    }
+}
+
+/// Reads one integer per line from stdin.
+pub struct StdinInput;
+
+impl IntcodeInput for StdinInput {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        let mut input_line = String::new();
+        match stdin().read_line(&mut input_line) {
+            Ok(0) | Err(_) => return Err(DeviceError::Exhausted),
+            Ok(_) => (),
+        }
+
+        let input = input_line.trim();
+        input
+            .parse()
+            .map_err(|_| DeviceError::Invalid(input.to_string()))
+    }
+}
+
+/// A bounded FIFO queue that drops the oldest value when full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingBuffer {
+    values: VecDeque<i64>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &i64> {
+        self.values.iter()
+    }
+}
+
+impl IntcodeInput for RingBuffer {
+    fn read(&mut self) -> Result<i64, DeviceError> {
+        self.values.pop_front().ok_or(DeviceError::Exhausted)
+    }
+}
+
+impl IntcodeOutput for RingBuffer {
+    fn write(&mut self, value: i64) -> Result<(), DeviceError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        Ok(())
+    }
+}
+
+/// Ignores every value written.
+pub struct Discard;
+
+impl IntcodeOutput for Discard {
+    fn write(&mut self, _: i64) -> Result<(), DeviceError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{asm::assemble, cpu::Cpu};
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut buffer = RingBuffer::new(2);
+        for value in 1..=3 {
+            buffer.write(value).unwrap();
+        }
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(buffer.read(), Ok(2));
+        assert_eq!(buffer.read(), Ok(3));
+        assert_eq!(buffer.read(), Err(DeviceError::Exhausted));
+        assert!(buffer.is_empty());
+
+        let mut buffer = RingBuffer::new(0);
+        buffer.write(1).unwrap();
+        assert_eq!(buffer.len(), 0);
+    }
+
+    #[test]
+    fn test_devices() {
+        let program = assemble("in [0]\nout [0]\nin [0]\nout [0]\nhalt").unwrap();
+        let mut next = 0;
+        let mut outputs = Vec::new();
+        let input = FnInput(|| {
+            next += 10;
+            next
+        });
+        Cpu::new(program.clone())
+            .run_with(input, FnOutput(|value| outputs.push(value)))
+            .unwrap();
+        assert_eq!(outputs, vec![10, 20]);
+
+        let mut cpu = Cpu::new(program.clone());
+        cpu.run_with(IterInput([1, 2].into_iter()), Discard)
+            .unwrap();
+        assert!(cpu.outputs.is_empty());
+        assert_eq!(cpu.summary().outputs, 2);
+
+        let mut cpu = Cpu::new(program);
+        assert_eq!(
+            cpu.run_with(IterInput([1].into_iter()), Discard),
+            Err(CpuError::InputExhausted { pc: 4 })
+        );
+    }
+
+    #[test]
+    fn test_closed_channel() {
+        let program = assemble("in [0]\nout [0]\nhalt").unwrap();
+
+        let (tx, rx) = channel();
+        drop(tx);
+        assert_eq!(
+            Cpu::new(program.clone()).run_with(ChannelInput(rx), Discard),
+            Err(CpuError::ChannelClosed { pc: 0 })
+        );
+
+        let (tx, rx) = channel();
+        drop(rx);
+        assert_eq!(
+            Cpu::new(program).run_with(IterInput([1].into_iter()), ChannelOutput(tx)),
+            Err(CpuError::ChannelClosed { pc: 2 })
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use tinyjson::JsonValue;
+
+/// Largest magnitude that survives a round trip through an `f64` JSON number.
+const MAX_SAFE_INT: i64 = 1 << 53;
+
+pub(super) fn int_to_json(value: i64) -> JsonValue {
+    if (-MAX_SAFE_INT..=MAX_SAFE_INT).contains(&value) {
+        JsonValue::Number(value as f64)
+    } else {
+        JsonValue::String(value.to_string())
+    }
+}
+
+pub(super) fn int_from_json(value: &JsonValue) -> Option<i64> {
+    match value {
+        JsonValue::Number(n) => Some(*n as i64),
+        JsonValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(super) fn opt_int_to_json(value: Option<i64>) -> JsonValue {
+    value.map(int_to_json).unwrap_or(JsonValue::Null)
+}
+
+pub(super) fn opt_int_from_json(value: Option<&JsonValue>) -> Result<Option<i64>, String> {
+    match value {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(value) => int_from_json(value)
+            .map(Some)
+            .ok_or_else(|| "expected an integer.".to_string()),
+    }
+}
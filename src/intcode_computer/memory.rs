@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+/// Backing storage of a [`Cpu`](super::cpu::Cpu). Unset addresses read as `0`.
+pub trait Memory: FromIterator<i64> + Clone {
+    fn get(&self, addr: usize) -> i64;
+    fn get_mut(&mut self, addr: usize) -> &mut i64;
+
+    /// All stored words as `(addr, value)` pairs, sorted by address.
+    fn words(&self) -> Vec<(usize, i64)>;
+
+    fn set(&mut self, addr: usize, value: i64) {
+        *self.get_mut(addr) = value;
+    }
+}
+
+/// Addresses below this limit are stored in a contiguous `Vec`, everything above in a map.
+const FLAT_LIMIT: usize = 1 << 20;
+
+/// Vec-backed memory that grows on demand, with a sparse overflow map for very high addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlatMemory {
+    words: Vec<i64>,
+    overflow: HashMap<usize, i64>,
+}
+
+impl Memory for FlatMemory {
+    fn get(&self, addr: usize) -> i64 {
+        match self.words.get(addr) {
+            Some(value) => *value,
+            None => *self.overflow.get(&addr).unwrap_or(&0),
+        }
+    }
+
+    fn get_mut(&mut self, addr: usize) -> &mut i64 {
+        if addr >= self.words.len() {
+            if addr >= FLAT_LIMIT {
+                return self.overflow.entry(addr).or_default();
+            }
+            self.words.resize(addr + 1, 0);
+        }
+        &mut self.words[addr]
+    }
+
+    fn words(&self) -> Vec<(usize, i64)> {
+        let mut overflow: Vec<_> = self.overflow.iter().map(|(&a, &v)| (a, v)).collect();
+        overflow.sort_unstable();
+        self.words
+            .iter()
+            .copied()
+            .enumerate()
+            .chain(overflow)
+            .collect()
+    }
+}
+
+impl FromIterator<i64> for FlatMemory {
+    fn from_iter<T: IntoIterator<Item = i64>>(iter: T) -> Self {
+        Self {
+            words: iter.into_iter().collect(),
+            overflow: HashMap::new(),
+        }
+    }
+}
+
+/// HashMap-backed memory, only storing addresses that have been written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparseMemory(HashMap<usize, i64>);
+
+impl Memory for SparseMemory {
+    fn get(&self, addr: usize) -> i64 {
+        *self.0.get(&addr).unwrap_or(&0)
+    }
+
+    fn get_mut(&mut self, addr: usize) -> &mut i64 {
+        self.0.entry(addr).or_default()
+    }
+
+    fn words(&self) -> Vec<(usize, i64)> {
+        let mut words: Vec<_> = self.0.iter().map(|(&a, &v)| (a, v)).collect();
+        words.sort_unstable();
+        words
+    }
+}
+
+impl FromIterator<i64> for SparseMemory {
+    fn from_iter<T: IntoIterator<Item = i64>>(iter: T) -> Self {
+        Self(iter.into_iter().enumerate().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_limit() {
+        let program = [1, 2, 3];
+        let mut flat: FlatMemory = program.into_iter().collect();
+        let mut sparse: SparseMemory = program.into_iter().collect();
+        let written = [
+            10,
+            FLAT_LIMIT - 1,
+            FLAT_LIMIT,
+            FLAT_LIMIT + 1,
+            usize::MAX / 2,
+            usize::MAX,
+        ];
+        for (i, &addr) in written.iter().enumerate() {
+            flat.set(addr, i as i64 + 10);
+            sparse.set(addr, i as i64 + 10);
+        }
+        *flat.get_mut(FLAT_LIMIT) += 5;
+        *sparse.get_mut(FLAT_LIMIT) += 5;
+
+        // the flat part grows up to the limit, addresses above it are only stored when written.
+        assert_eq!(flat.words.len(), FLAT_LIMIT);
+        assert_eq!(flat.overflow.len(), 4);
+        let unwritten = [3, 11, FLAT_LIMIT - 2, FLAT_LIMIT + 2, usize::MAX - 1];
+        for addr in written.into_iter().chain(unwritten).chain(0..3) {
+            assert_eq!(flat.get(addr), sparse.get(addr), "at {addr}");
+        }
+        assert!(unwritten.iter().all(|&addr| flat.get(addr) == 0));
+        assert_eq!(flat.overflow.len(), 4);
+
+        let nonzero: Vec<_> = flat
+            .words()
+            .into_iter()
+            .filter(|&(_, value)| value != 0)
+            .collect();
+        assert_eq!(nonzero, sparse.words());
+    }
+}
@@ -1,2 +1,23 @@
+pub mod analysis;
+pub mod ascii;
+pub mod asm;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod compile;
 pub mod cpu;
+pub mod debugger;
+pub mod decompile;
+pub mod disasm;
+pub mod error;
+pub mod fuzz;
+pub mod io;
+mod json;
+pub mod memory;
+pub mod network;
 pub mod opcode;
+pub mod pipeline;
+pub mod profile;
+pub mod program;
+pub mod snapshot;
+pub mod trace;
+pub mod watch;
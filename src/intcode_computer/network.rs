@@ -0,0 +1,254 @@
+use super::cpu::{Cpu, Event};
+use super::error::CpuError;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub dest: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// What the network should do after notifying a [`Monitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action<T> {
+    Continue,
+    Inject(Packet),
+    Stop(T),
+}
+
+/// Observes a [`Network`], e.g. a NAT that takes packets addressed outside the network and
+/// wakes the network up when it is idle.
+pub trait Monitor {
+    type Output;
+
+    /// Called for every packet addressed to a machine that is not part of the network.
+    fn packet(&mut self, packet: Packet) -> Action<Self::Output>;
+
+    /// Called when every queue is empty and every machine polled without receiving a packet.
+    fn idle(&mut self) -> Action<Self::Output>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MachineStats {
+    pub sent: usize,
+    pub received: usize,
+    pub idle_polls: usize,
+    pub steps: u64,
+}
+
+pub struct Machine {
+    pub cpu: Cpu,
+    queue: VecDeque<(i64, i64)>,
+    partial: Vec<i64>,
+    halted: bool,
+    stats: MachineStats,
+}
+
+/// Machines addressed by their index, exchanging `(dest, x, y)` packets. Every machine reads its
+/// address first, then either the next queued packet or `-1` if its queue is empty.
+///
+/// Machines are scheduled round-robin, each running until its next input request, so a run is
+/// fully deterministic.
+pub struct Network {
+    pub machines: Vec<Machine>,
+}
+
+impl Network {
+    pub fn new(program: &[i64], size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut cpu = Cpu::new(program.to_vec());
+                cpu.provide_input(address as i64);
+                Machine {
+                    cpu,
+                    queue: VecDeque::new(),
+                    partial: Vec::with_capacity(3),
+                    halted: false,
+                    stats: MachineStats::default(),
+                }
+            })
+            .collect();
+        Self { machines }
+    }
+
+    /// Queues a packet for its destination. Returns the packet if no such machine exists.
+    pub fn send(&mut self, packet: Packet) -> Option<Packet> {
+        let machine = usize::try_from(packet.dest)
+            .ok()
+            .and_then(|dest| self.machines.get_mut(dest));
+        match machine {
+            Some(machine) => {
+                machine.queue.push_back((packet.x, packet.y));
+                None
+            }
+            None => Some(packet),
+        }
+    }
+
+    pub fn stats(&self) -> Vec<MachineStats> {
+        self.machines
+            .iter()
+            .map(|machine| MachineStats {
+                steps: machine.cpu.steps(),
+                ..machine.stats
+            })
+            .collect()
+    }
+
+    fn apply<T>(&mut self, action: Action<T>) -> Option<T> {
+        match action {
+            Action::Continue => None,
+            Action::Inject(packet) => {
+                self.send(packet);
+                None
+            }
+            Action::Stop(value) => Some(value),
+        }
+    }
+
+    /// Runs until the monitor stops the network. Returns `None` if every machine halted first.
+    pub fn run<M: Monitor>(&mut self, monitor: &mut M) -> Result<Option<M::Output>, CpuError> {
+        loop {
+            let mut idle = true;
+
+            for i in 0..self.machines.len() {
+                let mut outgoing = Vec::new();
+                let machine = &mut self.machines[i];
+                if machine.halted {
+                    continue;
+                }
+
+                loop {
+                    match machine.cpu.resume()? {
+                        Event::Output(value) => {
+                            machine.partial.push(value);
+                            if let [dest, x, y] = machine.partial[..] {
+                                machine.partial.clear();
+                                machine.stats.sent += 1;
+                                outgoing.push(Packet { dest, x, y });
+                            }
+                        }
+                        Event::NeedsInput => {
+                            match machine.queue.pop_front() {
+                                Some((x, y)) => {
+                                    machine.cpu.provide_input(x);
+                                    machine.cpu.provide_input(y);
+                                    machine.stats.received += 1;
+                                    idle = false;
+                                }
+                                None => {
+                                    machine.cpu.provide_input(-1);
+                                    machine.stats.idle_polls += 1;
+                                }
+                            }
+                            break;
+                        }
+                        Event::Halted => {
+                            machine.halted = true;
+                            break;
+                        }
+                    }
+                }
+
+                for packet in outgoing {
+                    idle = false;
+                    if let Some(packet) = self.send(packet) {
+                        if let Some(value) = self.apply(monitor.packet(packet)) {
+                            return Ok(Some(value));
+                        }
+                    }
+                }
+            }
+
+            if self.machines.iter().all(|machine| machine.halted) {
+                return Ok(None);
+            }
+
+            let queues_empty = self.machines.iter().all(|m| m.queue.is_empty());
+            if idle && queues_empty {
+                if let Some(value) = self.apply(monitor.idle()) {
+                    return Ok(Some(value));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+
+    #[derive(Default)]
+    struct Nat {
+        packets: Vec<Packet>,
+        woken: bool,
+    }
+
+    impl Monitor for Nat {
+        type Output = Vec<Packet>;
+
+        fn packet(&mut self, packet: Packet) -> Action<Self::Output> {
+            self.packets.push(packet);
+            Action::Continue
+        }
+
+        fn idle(&mut self) -> Action<Self::Output> {
+            if self.woken {
+                return Action::Stop(std::mem::take(&mut self.packets));
+            }
+            self.woken = true;
+            Action::Inject(Packet {
+                dest: 0,
+                x: 7,
+                y: 7,
+            })
+        }
+    }
+
+    #[test]
+    fn test_network() {
+        let program = assemble(
+            "      in [a]
+                   add [a], #1, [d]
+                   mul [a], #10, [y]
+                   out [d]
+                   out [a]
+                   out [y]
+             loop: in [x]
+                   eq [x], #-1, [t]
+                   jt [t], #loop
+                   in [y]
+                   add [y], #1, [y]
+                   out #255
+                   out [x]
+                   out [y]
+                   jt #1, #loop
+             a:    data 0
+             d:    data 0
+             x:    data 0
+             y:    data 0
+             t:    data 0",
+        )
+        .unwrap();
+
+        let mut network = Network::new(&program, 3);
+        let packets = network.run(&mut Nat::default()).unwrap().unwrap();
+        let packet = |dest, x, y| Packet { dest, x, y };
+        assert_eq!(
+            packets,
+            vec![
+                packet(3, 2, 20),
+                packet(255, 0, 1),
+                packet(255, 1, 11),
+                packet(255, 7, 8)
+            ]
+        );
+
+        for stats in network.stats() {
+            assert_eq!((stats.sent, stats.received, stats.idle_polls), (2, 1, 5));
+            assert!(stats.steps > 0);
+        }
+    }
+}
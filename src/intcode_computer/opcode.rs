@@ -1,3 +1,7 @@
+use std::fmt::Display;
+use OpCode::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OpCode {
     Add,
     Mul,
@@ -11,20 +15,86 @@ pub enum OpCode {
     Halt,
 }
 
-impl From<i64> for OpCode {
-    fn from(code: i64) -> Self {
+impl TryFrom<i64> for OpCode {
+    type Error = i64;
+
+    fn try_from(code: i64) -> Result<Self, Self::Error> {
         match code % 100 {
-            1 => Self::Add,
-            2 => Self::Mul,
-            3 => Self::In,
-            4 => Self::Out,
-            5 => Self::Jt,
-            6 => Self::Jf,
-            7 => Self::Lt,
-            8 => Self::Eq,
-            9 => Self::Rb,
-            99 => Self::Halt,
-            _ => unreachable!(),
+            1 => Ok(Self::Add),
+            2 => Ok(Self::Mul),
+            3 => Ok(Self::In),
+            4 => Ok(Self::Out),
+            5 => Ok(Self::Jt),
+            6 => Ok(Self::Jf),
+            7 => Ok(Self::Lt),
+            8 => Ok(Self::Eq),
+            9 => Ok(Self::Rb),
+            99 => Ok(Self::Halt),
+            _ => Err(code),
+        }
+    }
+}
+
+impl OpCode {
+    pub const ALL: [OpCode; 10] = [Add, Mul, In, Out, Jt, Jf, Lt, Eq, Rb, Halt];
+
+    pub fn code(&self) -> i64 {
+        match self {
+            Add => 1,
+            Mul => 2,
+            In => 3,
+            Out => 4,
+            Jt => 5,
+            Jf => 6,
+            Lt => 7,
+            Eq => 8,
+            Rb => 9,
+            Halt => 99,
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.mnemonic() == mnemonic)
+    }
+
+    /// Number of parameters following the instruction word.
+    pub fn params(&self) -> usize {
+        match self {
+            Add | Mul | Lt | Eq => 3,
+            Jt | Jf => 2,
+            In | Out | Rb => 1,
+            Halt => 0,
         }
     }
+
+    /// Whether the last parameter is a write destination.
+    pub fn writes(&self) -> bool {
+        matches!(self, Add | Mul | Lt | Eq | In)
+    }
+
+    /// Number of parameters that are read.
+    pub fn reads(&self) -> usize {
+        self.params() - self.writes() as usize
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Add => "add",
+            Mul => "mul",
+            In => "in",
+            Out => "out",
+            Jt => "jt",
+            Jf => "jf",
+            Lt => "lt",
+            Eq => "eq",
+            Rb => "rb",
+            Halt => "halt",
+        }
+    }
+}
+
+impl Display for OpCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic())
+    }
 }
@@ -0,0 +1,162 @@
+use super::cpu::{Cpu, Event};
+use super::error::CpuError;
+
+struct Node {
+    cpu: Cpu,
+    targets: Vec<usize>,
+    blocked: bool,
+    halted: bool,
+}
+
+/// Machines whose outputs are forwarded to the inputs of other machines, e.g. a chain or ring of
+/// amplifiers. Nodes are identified by the index returned from [`Pipeline::add`].
+#[derive(Default)]
+pub struct Pipeline {
+    nodes: Vec<Node>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a machine running `program` that first reads `inputs`, e.g. a phase setting.
+    pub fn add(&mut self, program: &[i64], inputs: &[i64]) -> usize {
+        let mut cpu = Cpu::new(program.to_vec());
+        for &value in inputs {
+            cpu.provide_input(value);
+        }
+        self.nodes.push(Node {
+            cpu,
+            targets: Vec::new(),
+            blocked: false,
+            halted: false,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Forwards every output of `from` to the input of `to`.
+    pub fn connect(&mut self, from: usize, to: usize) -> &mut Self {
+        self.nodes[from].targets.push(to);
+        self
+    }
+
+    /// One machine per phase, each feeding the next.
+    pub fn chain(program: &[i64], phases: &[i64]) -> Self {
+        let mut pipeline = Self::new();
+        for &phase in phases {
+            let node = pipeline.add(program, &[phase]);
+            if node > 0 {
+                pipeline.connect(node - 1, node);
+            }
+        }
+        pipeline
+    }
+
+    /// A [`Pipeline::chain`] whose last machine feeds back into the first.
+    pub fn ring(program: &[i64], phases: &[i64]) -> Self {
+        let mut pipeline = Self::chain(program, phases);
+        if let Some(last) = pipeline.nodes.len().checked_sub(1) {
+            pipeline.connect(last, 0);
+        }
+        pipeline
+    }
+
+    pub fn input(&mut self, node: usize, value: i64) -> &mut Self {
+        self.nodes[node].cpu.provide_input(value);
+        self
+    }
+
+    pub fn cpu(&self, node: usize) -> &Cpu {
+        &self.nodes[node].cpu
+    }
+
+    /// The last value written by `node`.
+    pub fn output(&self, node: usize) -> Option<i64> {
+        self.nodes[node].cpu.outputs.last().copied()
+    }
+
+    /// Runs every machine round-robin until all of them halted. Fails with
+    /// [`CpuError::InputExhausted`] if the remaining machines all wait for input that no other
+    /// machine can provide.
+    pub fn run(&mut self) -> Result<(), CpuError> {
+        loop {
+            let mut progress = false;
+
+            for i in 0..self.nodes.len() {
+                let node = &mut self.nodes[i];
+                if node.halted || (node.blocked && node.cpu.pending_inputs() == 0) {
+                    continue;
+                }
+                progress = true;
+
+                let mut outputs = Vec::new();
+                loop {
+                    match node.cpu.resume()? {
+                        Event::Output(value) => outputs.push(value),
+                        Event::NeedsInput => {
+                            node.blocked = true;
+                            break;
+                        }
+                        Event::Halted => {
+                            node.halted = true;
+                            break;
+                        }
+                    }
+                }
+
+                for target in node.targets.clone() {
+                    for &value in &outputs {
+                        self.nodes[target].cpu.provide_input(value);
+                    }
+                }
+            }
+
+            if let Some(node) = self.nodes.iter().find(|node| !node.halted) {
+                if !progress {
+                    return Err(CpuError::InputExhausted { pc: node.cpu.pc() });
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::asm::assemble;
+
+    #[test]
+    fn test_pipeline_dag() {
+        let adder = assemble(
+            "in [a]
+             in [b]
+             add [a], [b], [a]
+             out [a]
+             halt
+             a: data 0
+             b: data 0",
+        )
+        .unwrap();
+
+        let mut pipeline = Pipeline::new();
+        let left = pipeline.add(&adder, &[1, 2]);
+        let right = pipeline.add(&adder, &[3, 4]);
+        let sum = pipeline.add(&adder, &[]);
+        let offset = pipeline.add(&adder, &[100]);
+        pipeline
+            .connect(left, sum)
+            .connect(right, sum)
+            .connect(left, offset);
+        pipeline.run().unwrap();
+        assert_eq!(pipeline.output(sum), Some(10));
+        assert_eq!(pipeline.output(offset), Some(103));
+
+        let mut starved = Pipeline::new();
+        let node = starved.add(&adder, &[1]);
+        assert_eq!(starved.run(), Err(CpuError::InputExhausted { pc: 2 }));
+        assert_eq!(starved.output(node), None);
+    }
+}
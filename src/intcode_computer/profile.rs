@@ -0,0 +1,167 @@
+use super::json::int_to_json;
+use super::opcode::OpCode;
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::{fs, io, path::Path};
+use tinyjson::JsonValue;
+
+/// Number of addresses listed as hot spots in the report.
+const HOT_SPOTS: usize = 10;
+
+/// Execution counters collected by [`Cpu::record_profile`](super::cpu::Cpu::record_profile).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub instructions: u64,
+    /// Operand reads from memory, i.e. excluding immediate parameters.
+    pub reads: u64,
+    pub writes: u64,
+    pub per_opcode: HashMap<OpCode, u64>,
+    pub per_address: HashMap<usize, u64>,
+}
+
+impl Profile {
+    pub(super) fn record(&mut self, pc: usize, opcode: OpCode, reads: usize) {
+        self.instructions += 1;
+        self.reads += reads as u64;
+        *self.per_opcode.entry(opcode).or_default() += 1;
+        *self.per_address.entry(pc).or_default() += 1;
+    }
+
+    /// Reverts [`Profile::record`] for an `In` instruction that has to wait for input.
+    pub(super) fn retract(&mut self, pc: usize) {
+        self.instructions -= 1;
+        decrement(&mut self.per_opcode, OpCode::In);
+        decrement(&mut self.per_address, pc);
+    }
+
+    /// Opcodes by execution count, most frequent first.
+    pub fn opcodes(&self) -> Vec<(OpCode, u64)> {
+        let mut opcodes: Vec<_> = self.per_opcode.iter().map(|(&op, &n)| (op, n)).collect();
+        opcodes.sort_unstable_by_key(|&(op, n)| (std::cmp::Reverse(n), op.code()));
+        opcodes
+    }
+
+    /// Addresses by execution count, most frequent first.
+    pub fn hot_spots(&self) -> Vec<(usize, u64)> {
+        let mut addresses: Vec<_> = self.per_address.iter().map(|(&a, &n)| (a, n)).collect();
+        addresses.sort_unstable_by_key(|&(addr, n)| (std::cmp::Reverse(n), addr));
+        addresses
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = JsonValue::from(self)
+            .stringify()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        fs::write(path, json)
+    }
+}
+
+/// Counts one execution less for `key`, dropping it once it was never executed.
+fn decrement<K: Eq + Hash>(counts: &mut HashMap<K, u64>, key: K) {
+    if let Entry::Occupied(mut entry) = counts.entry(key) {
+        *entry.get_mut() -= 1;
+        if *entry.get() == 0 {
+            entry.remove();
+        }
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let share = |n: u64| 100.0 * n as f64 / self.instructions.max(1) as f64;
+
+        writeln!(f, "instructions: {}", self.instructions)?;
+        writeln!(f, "memory reads: {}", self.reads)?;
+        writeln!(f, "memory writes: {}", self.writes)?;
+        writeln!(f)?;
+        writeln!(f, "{:<6} {:>12} {:>7}", "opcode", "count", "share")?;
+        for (opcode, n) in self.opcodes() {
+            writeln!(f, "{opcode:<6} {n:>12} {:>6.2}%", share(n))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:<6} {:>12} {:>7}", "addr", "count", "share")?;
+        for (addr, n) in self.hot_spots().into_iter().take(HOT_SPOTS) {
+            writeln!(f, "{addr:<6} {n:>12} {:>6.2}%", share(n))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Profile> for JsonValue {
+    fn from(profile: &Profile) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert(
+            "instructions".into(),
+            int_to_json(profile.instructions as i64),
+        );
+        map.insert("reads".into(), int_to_json(profile.reads as i64));
+        map.insert("writes".into(), int_to_json(profile.writes as i64));
+        map.insert(
+            "per_opcode".into(),
+            JsonValue::Object(
+                profile
+                    .per_opcode
+                    .iter()
+                    .map(|(op, &n)| (op.mnemonic().to_string(), int_to_json(n as i64)))
+                    .collect(),
+            ),
+        );
+        map.insert(
+            "per_address".into(),
+            JsonValue::Object(
+                profile
+                    .per_address
+                    .iter()
+                    .map(|(addr, &n)| (addr.to_string(), int_to_json(n as i64)))
+                    .collect(),
+            ),
+        );
+
+        JsonValue::Object(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{
+        asm::assemble,
+        cpu::{Cpu, Event},
+        program::Program,
+    };
+
+    #[test]
+    fn test_profile() {
+        let program = Program::load("data/examples/09-1.txt").unwrap().into_vec();
+        let mut cpu = Cpu::new(program.clone());
+        cpu.record_profile();
+        cpu.run(None).unwrap();
+
+        let profile = cpu.profile.as_ref().unwrap();
+        assert_eq!(profile.instructions, cpu.steps());
+        assert_eq!(profile.per_opcode[&OpCode::Out], program.len() as u64);
+        assert_eq!(profile.per_opcode[&OpCode::Halt], 1);
+        assert_eq!(
+            profile.hot_spots().iter().map(|(_, n)| n).sum::<u64>(),
+            profile.instructions
+        );
+    }
+
+    #[test]
+    fn test_profile_waiting_for_input() {
+        let mut cpu = Cpu::new(assemble("out #1\nin [0]\nhalt").unwrap());
+        cpu.record_profile();
+        assert_eq!(cpu.resume(), Ok(Event::Output(1)));
+        assert_eq!(cpu.resume(), Ok(Event::NeedsInput));
+
+        // the blocked `in` was not executed, so it is not listed at all.
+        let profile = cpu.profile.as_ref().unwrap();
+        assert_eq!(profile.instructions, 1);
+        assert!(!profile.per_opcode.contains_key(&OpCode::In));
+        assert!(!profile.per_address.contains_key(&2));
+        let json = JsonValue::from(profile).stringify().unwrap();
+        assert!(!json.contains("\"in\""));
+    }
+}
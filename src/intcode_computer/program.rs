@@ -0,0 +1,157 @@
+use std::fmt::Display;
+use std::ops::Deref;
+use std::{fs, path::Path};
+
+const WORD_SIZE: usize = std::mem::size_of::<i64>();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// `line` and `column` are 1-based and point at the start of the token.
+    InvalidToken {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    Empty,
+    /// The length of a binary dump is not a multiple of 8 bytes.
+    TruncatedWord {
+        len: usize,
+    },
+    Io(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidToken {
+                line,
+                column,
+                token,
+            } => write!(f, "line {line}, column {column}: invalid integer {token:?}"),
+            ParseError::Empty => write!(f, "program is empty"),
+            ParseError::TruncatedWord { len } => {
+                write!(f, "binary program of {len} bytes ends in a partial word")
+            }
+            ParseError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The initial memory of an Intcode machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program(pub Vec<i64>);
+
+impl Program {
+    /// Parses comma-separated integers. Whitespace and newlines between tokens, trailing commas
+    /// at the end of a line and `#` comments are ignored.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut words = Vec::new();
+        for (line_index, line) in input.lines().enumerate() {
+            let code = line.split('#').next().unwrap_or_default();
+            let tokens: Vec<_> = code.split(',').collect();
+            let mut column = 1;
+            for (i, raw) in tokens.iter().enumerate() {
+                let token = raw.trim();
+                if token.is_empty() && i == tokens.len() - 1 {
+                    break;
+                }
+                let value = token.parse().map_err(|_| ParseError::InvalidToken {
+                    line: line_index + 1,
+                    column: column + raw.len() - raw.trim_start().len(),
+                    token: token.to_string(),
+                })?;
+                words.push(value);
+                column += raw.len() + 1;
+            }
+        }
+
+        if words.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        Ok(Self(words))
+    }
+
+    /// Reads a dump of little-endian 64-bit words.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if !bytes.len().is_multiple_of(WORD_SIZE) {
+            return Err(ParseError::TruncatedWord { len: bytes.len() });
+        }
+        if bytes.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let words = bytes
+            .chunks_exact(WORD_SIZE)
+            .map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Ok(Self(words))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// Loads a binary dump if `path` has a `.bin` extension, and text otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParseError> {
+        let path = path.as_ref();
+        let io_error = |e: std::io::Error| ParseError::Io(format!("{}: {e}", path.display()));
+        if path.extension().is_some_and(|ext| ext == "bin") {
+            Self::from_bytes(&fs::read(path).map_err(io_error)?)
+        } else {
+            Self::parse(&fs::read_to_string(path).map_err(io_error)?)
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<i64> {
+        self.0
+    }
+}
+
+impl Deref for Program {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Program> for Vec<i64> {
+    fn from(program: Program) -> Self {
+        program.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_parse() {
+        let program = Program::parse("# header\n1, 2,\n  3 ,4 # trailing\n\n99\n").unwrap();
+        assert_eq!(&*program, &[1, 2, 3, 4, 99]);
+        assert_eq!(Program::from_bytes(&program.to_bytes()), Ok(program));
+
+        assert_eq!(
+            Program::parse("1,2\n3, x4,5"),
+            Err(ParseError::InvalidToken {
+                line: 2,
+                column: 4,
+                token: "x4".into()
+            })
+        );
+        assert_eq!(
+            Program::parse("1,,2"),
+            Err(ParseError::InvalidToken {
+                line: 1,
+                column: 3,
+                token: "".into()
+            })
+        );
+        assert_eq!(Program::parse(" # nothing\n"), Err(ParseError::Empty));
+        assert_eq!(
+            Program::from_bytes(&[0; 12]),
+            Err(ParseError::TruncatedWord { len: 12 })
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use super::json::{int_from_json, int_to_json};
+use super::memory::{FlatMemory, Memory};
+use std::collections::HashMap;
+use std::{fs, io, path::Path};
+use tinyjson::JsonValue;
+
+/// The complete state of a [`Cpu`](super::cpu::Cpu), see [`Cpu::snapshot`](super::cpu::Cpu::snapshot).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<M: Memory = FlatMemory> {
+    pub memory: M,
+    pub pc: usize,
+    pub relative_base: isize,
+    pub outputs: Vec<i64>,
+    pub pending_inputs: Vec<i64>,
+}
+
+fn ints_to_json(values: &[i64]) -> JsonValue {
+    JsonValue::Array(values.iter().copied().map(int_to_json).collect())
+}
+
+fn ints_from_json(value: Option<&JsonValue>, key: &str) -> Result<Vec<i64>, String> {
+    value
+        .and_then(|value| value.get::<Vec<JsonValue>>())
+        .and_then(|values| values.iter().map(int_from_json).collect())
+        .ok_or_else(|| format!("expected `{key}` to be an array of integers."))
+}
+
+impl<M: Memory> From<&Snapshot<M>> for JsonValue {
+    fn from(snapshot: &Snapshot<M>) -> Self {
+        // store memory as runs of consecutive addresses to keep the file compact.
+        let mut segments: Vec<(usize, Vec<i64>)> = Vec::new();
+        for (addr, value) in snapshot.memory.words() {
+            match segments.last_mut() {
+                Some((start, values)) if *start + values.len() == addr => values.push(value),
+                _ => segments.push((addr, vec![value])),
+            }
+        }
+
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+        map.insert("pc".into(), int_to_json(snapshot.pc as i64));
+        map.insert(
+            "relative_base".into(),
+            int_to_json(snapshot.relative_base as i64),
+        );
+        map.insert("outputs".into(), ints_to_json(&snapshot.outputs));
+        map.insert(
+            "pending_inputs".into(),
+            ints_to_json(&snapshot.pending_inputs),
+        );
+        map.insert(
+            "memory".into(),
+            JsonValue::Array(
+                segments
+                    .iter()
+                    .map(|(start, values)| {
+                        JsonValue::Array(vec![int_to_json(*start as i64), ints_to_json(values)])
+                    })
+                    .collect(),
+            ),
+        );
+
+        JsonValue::Object(map)
+    }
+}
+
+impl<M: Memory> TryFrom<&JsonValue> for Snapshot<M> {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("expected snapshot to be a JSON object.")?;
+
+        let pc = json
+            .get("pc")
+            .and_then(int_from_json)
+            .and_then(|pc| usize::try_from(pc).ok())
+            .ok_or("expected `pc` to be an address.")?;
+        let relative_base =
+            json.get("relative_base")
+                .and_then(int_from_json)
+                .ok_or("expected `relative_base` to be an integer.")? as isize;
+
+        let mut memory: M = std::iter::empty().collect();
+        let segments = json
+            .get("memory")
+            .and_then(|memory| memory.get::<Vec<JsonValue>>())
+            .ok_or("expected `memory` to be an array.")?;
+        for segment in segments {
+            let (start, values) = match segment.get::<Vec<JsonValue>>().map(Vec::as_slice) {
+                Some([start, values]) => (
+                    int_from_json(start)
+                        .and_then(|start| usize::try_from(start).ok())
+                        .ok_or("expected memory segment to start at an address.")?,
+                    ints_from_json(Some(values), "memory")?,
+                ),
+                _ => return Err("expected `memory` to contain `[addr, values]` pairs.".into()),
+            };
+            for (i, value) in values.into_iter().enumerate() {
+                memory.set(start + i, value);
+            }
+        }
+
+        Ok(Snapshot {
+            memory,
+            pc,
+            relative_base,
+            outputs: ints_from_json(json.get("outputs"), "outputs")?,
+            pending_inputs: ints_from_json(json.get("pending_inputs"), "pending_inputs")?,
+        })
+    }
+}
+
+impl<M: Memory> Snapshot<M> {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = JsonValue::from(self)
+            .stringify()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let json: JsonValue = contents.parse().or(Err("not valid JSON file."))?;
+        Snapshot::try_from(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{
+        cpu::{Cpu, Event},
+        program::Program,
+    };
+    use std::path::PathBuf;
+
+    /// A file in the temp dir that is unique to this process and removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("aoc2019-{}-{name}", std::process::id());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let program = Program::load("data/examples/09-1.txt").unwrap().into_vec();
+        let mut cpu = Cpu::new(program.clone());
+        for _ in 0..5 {
+            assert!(matches!(cpu.resume(), Ok(Event::Output(_))));
+        }
+
+        let snapshot = cpu.snapshot();
+        let file = TempFile::new("snapshot.json");
+        snapshot.save(&file.0).unwrap();
+        let loaded: Snapshot = Snapshot::load(&file.0).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        cpu.run(None).unwrap();
+        assert_eq!(cpu.outputs, program);
+
+        cpu.restore(loaded);
+        assert_eq!(cpu.outputs.len(), 5);
+        cpu.run(None).unwrap();
+        assert_eq!(cpu.outputs, program);
+    }
+}
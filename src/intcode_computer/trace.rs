@@ -0,0 +1,190 @@
+use super::cpu::Cpu;
+use super::error::CpuError;
+use super::json::{int_from_json, int_to_json, opt_int_from_json, opt_int_to_json};
+use super::opcode::OpCode;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use tinyjson::JsonValue;
+
+/// A single executed instruction, as recorded by [`Cpu::record_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: usize,
+    pub opcode: OpCode,
+    /// Values of the parameters that are read, in order.
+    pub operands: Vec<i64>,
+    /// Memory writes as `(addr, value)` pairs.
+    pub writes: Vec<(usize, i64)>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+}
+
+impl From<&TraceStep> for JsonValue {
+    fn from(step: &TraceStep) -> Self {
+        let mut map: HashMap<String, JsonValue> = HashMap::new();
+
+        map.insert("pc".into(), int_to_json(step.pc as i64));
+        map.insert(
+            "op".into(),
+            JsonValue::String(step.opcode.mnemonic().into()),
+        );
+        map.insert(
+            "operands".into(),
+            JsonValue::Array(step.operands.iter().copied().map(int_to_json).collect()),
+        );
+        map.insert(
+            "writes".into(),
+            JsonValue::Array(
+                step.writes
+                    .iter()
+                    .map(|&(addr, value)| {
+                        JsonValue::Array(vec![int_to_json(addr as i64), int_to_json(value)])
+                    })
+                    .collect(),
+            ),
+        );
+        map.insert("input".into(), opt_int_to_json(step.input));
+        map.insert("output".into(), opt_int_to_json(step.output));
+
+        JsonValue::Object(map)
+    }
+}
+
+impl TryFrom<&JsonValue> for TraceStep {
+    type Error = String;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        let json = value
+            .get::<HashMap<String, JsonValue>>()
+            .ok_or("expected trace step to be a JSON object.")?;
+
+        let pc = json
+            .get("pc")
+            .and_then(int_from_json)
+            .and_then(|pc| usize::try_from(pc).ok())
+            .ok_or("expected `pc` to be an address.")?;
+        let opcode = json
+            .get("op")
+            .and_then(|op| op.get::<String>())
+            .and_then(|op| OpCode::from_mnemonic(op))
+            .ok_or("expected `op` to be a mnemonic.")?;
+        let operands = json
+            .get("operands")
+            .and_then(|operands| operands.get::<Vec<JsonValue>>())
+            .ok_or("expected `operands` to be an array.")?
+            .iter()
+            .map(int_from_json)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("expected `operands` to contain integers.")?;
+        let writes = json
+            .get("writes")
+            .and_then(|writes| writes.get::<Vec<JsonValue>>())
+            .ok_or("expected `writes` to be an array.")?
+            .iter()
+            .map(|write| match write.get::<Vec<JsonValue>>()?.as_slice() {
+                [addr, value] => Some((
+                    usize::try_from(int_from_json(addr)?).ok()?,
+                    int_from_json(value)?,
+                )),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("expected `writes` to contain `[addr, value]` pairs.")?;
+
+        Ok(TraceStep {
+            pc,
+            opcode,
+            operands,
+            writes,
+            input: opt_int_from_json(json.get("input"))?,
+            output: opt_int_from_json(json.get("output"))?,
+        })
+    }
+}
+
+/// Writes a trace as JSON lines, one step per line.
+pub fn write_jsonl(trace: &[TraceStep], writer: &mut impl Write) -> io::Result<()> {
+    for step in trace {
+        let line = JsonValue::from(step)
+            .stringify()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
+}
+
+pub fn read_jsonl(reader: impl BufRead) -> Result<Vec<TraceStep>, String> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line.map_err(|e| e.to_string())?;
+            let json: JsonValue = line
+                .parse()
+                .map_err(|_| format!("line {}: not valid JSON.", i + 1))?;
+            TraceStep::try_from(&json).map_err(|e| format!("line {}: {e}", i + 1))
+        })
+        .collect()
+}
+
+/// The first step at which a replay differs from its recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: TraceStep,
+    pub actual: Option<TraceStep>,
+}
+
+/// Re-executes `program`, feeding it the inputs consumed in `trace`, and compares every executed
+/// instruction with the recording. Returns the first divergence, if any.
+pub fn replay(program: Vec<i64>, trace: &[TraceStep]) -> Result<Option<Divergence>, CpuError> {
+    let mut cpu = Cpu::new(program);
+    cpu.record_trace();
+    trace
+        .iter()
+        .filter_map(|step| step.input)
+        .for_each(|value| cpu.provide_input(value));
+
+    for (index, expected) in trace.iter().enumerate() {
+        cpu.step()?;
+        let actual = cpu.trace.as_mut().and_then(|trace| trace.pop());
+        if actual.as_ref() != Some(expected) {
+            return Ok(Some(Divergence {
+                index,
+                expected: expected.clone(),
+                actual,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::program::Program;
+
+    #[test]
+    fn test_trace_replay() {
+        let program = Program::load("data/examples/07-1.txt").unwrap().into_vec();
+        let mut cpu = Cpu::new(program.clone());
+        cpu.record_trace();
+        cpu.run(Some(vec![4, 0])).unwrap();
+
+        let mut jsonl = Vec::new();
+        write_jsonl(cpu.trace.as_ref().unwrap(), &mut jsonl).unwrap();
+        let mut recorded = read_jsonl(jsonl.as_slice()).unwrap();
+        assert_eq!(&recorded, cpu.trace.as_ref().unwrap());
+        assert_eq!(replay(program.clone(), &recorded), Ok(None));
+
+        let index = recorded
+            .iter()
+            .rposition(|step| step.output.is_some())
+            .unwrap();
+        recorded[index].output = Some(0);
+        let divergence = replay(program, &recorded).unwrap().unwrap();
+        assert_eq!(divergence.index, index);
+        assert_eq!(divergence.actual.unwrap().output, Some(4));
+    }
+}
@@ -0,0 +1,145 @@
+use super::cpu::Event;
+use std::fmt::Display;
+use std::ops::Range;
+
+/// A condition that interrupts [`Cpu::step_watched`](super::cpu::Cpu::step_watched) and
+/// [`Cpu::resume_watched`](super::cpu::Cpu::resume_watched) with a [`Hit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Watch {
+    /// An operand is read from an address in the range.
+    Read(Range<usize>),
+    /// An address in the range is written, even if the value does not change.
+    Write(Range<usize>),
+    /// An `rb` instruction is executed, even if it adds zero.
+    RelativeBase,
+    /// The instruction at the address is about to be executed.
+    Pc(usize),
+}
+
+/// Why [`Cpu::step_watched`](super::cpu::Cpu::step_watched) or
+/// [`Cpu::resume_watched`](super::cpu::Cpu::resume_watched) returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watched {
+    Event(Event),
+    Hit(Hit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    Read {
+        pc: usize,
+        addr: usize,
+        value: i64,
+    },
+    Write {
+        pc: usize,
+        addr: usize,
+        old: i64,
+        new: i64,
+    },
+    RelativeBase {
+        pc: usize,
+        old: isize,
+        new: isize,
+    },
+    Pc(usize),
+}
+
+impl Hit {
+    /// The address of the instruction that triggered the watchpoint.
+    pub fn pc(&self) -> usize {
+        match self {
+            Hit::Read { pc, .. } | Hit::Write { pc, .. } | Hit::RelativeBase { pc, .. } => *pc,
+            Hit::Pc(pc) => *pc,
+        }
+    }
+}
+
+impl Display for Hit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hit::Read { pc, addr, value } => write!(f, "read [{addr}] = {value} at pc {pc}"),
+            Hit::Write { pc, addr, old, new } => {
+                write!(f, "write [{addr}] {old} -> {new} at pc {pc}")
+            }
+            Hit::RelativeBase { pc, old, new } => {
+                write!(f, "relative base {old} -> {new} at pc {pc}")
+            }
+            Hit::Pc(pc) => write!(f, "reached pc {pc}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{
+        asm::assemble,
+        cpu::{Cpu, Event},
+    };
+
+    #[test]
+    fn test_watchpoints() {
+        let program = assemble(
+            "        rb #7
+                     in [score]
+                     mul [score], #100, [score]
+                     out [score]
+                     halt
+             score:  data 0",
+        )
+        .unwrap();
+
+        let mut cpu = Cpu::new(program);
+        cpu.watches = vec![
+            Watch::RelativeBase,
+            Watch::Write(11..12),
+            Watch::Read(11..12),
+            Watch::Pc(10),
+        ];
+        let hit = |hit| Ok(Watched::Hit(hit));
+        let event = |event| Ok(Watched::Event(event));
+        assert_eq!(
+            cpu.resume_watched(),
+            hit(Hit::RelativeBase {
+                pc: 0,
+                old: 0,
+                new: 7
+            })
+        );
+        assert_eq!(cpu.resume_watched(), event(Event::NeedsInput));
+
+        cpu.provide_input(3);
+        let write = |pc, old, new| {
+            hit(Hit::Write {
+                pc,
+                addr: 11,
+                old,
+                new,
+            })
+        };
+        let read = |pc, value| {
+            hit(Hit::Read {
+                pc,
+                addr: 11,
+                value,
+            })
+        };
+        assert_eq!(cpu.resume_watched(), write(2, 0, 3));
+        assert_eq!(cpu.resume_watched(), read(4, 3));
+        assert_eq!(cpu.resume_watched(), write(4, 3, 300));
+        assert_eq!(cpu.resume_watched(), event(Event::Output(300)));
+        assert_eq!(cpu.resume_watched(), read(8, 300));
+        assert_eq!(cpu.resume_watched(), hit(Hit::Pc(10)));
+        assert_eq!(cpu.resume_watched(), event(Event::Halted));
+
+        // plain stepping ignores watches.
+        let mut cpu = Cpu::new(assemble("loop: jt #1, #loop").unwrap());
+        cpu.watches = vec![Watch::Pc(0)];
+        assert_eq!(cpu.step(), Ok(None));
+        assert_eq!(cpu.step_watched(), Ok(Some(Watched::Hit(Hit::Pc(0)))));
+        assert_eq!(cpu.step_watched(), Ok(None));
+        assert_eq!(cpu.step_watched(), Ok(Some(Watched::Hit(Hit::Pc(0)))));
+        assert_eq!(cpu.steps(), 2);
+    }
+}
@@ -1,3 +1,6 @@
+// lets code generated for the crate's users, like `benches/compiled`, be tested in here.
+extern crate self as advent_of_code;
+
 pub mod intcode_computer;
 pub mod template;
 
@@ -7,6 +10,9 @@ use indicatif::{ProgressBar, ProgressStyle};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
 
+/// Logs events at `level` and above. Intcode machines open a span per run at `INFO`, and log a
+/// summary the first time they halt at `DEBUG`; with the `intcode-trace` feature, every
+/// instruction and output is logged at `DEBUG` as well.
 pub fn tracing_init(level: impl Into<LevelFilter>) {
     let subscriber = tracing_subscriber::fmt()
         .with_max_level(level)
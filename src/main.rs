@@ -1,5 +1,5 @@
-use advent_of_code::template::commands::{all, download, read, scaffold, solve, time};
-use args::{parse, AppArguments};
+use advent_of_code::template::commands::{all, download, intcode, read, scaffold, solve, time};
+use args::{parse, AppArguments, IntcodeCommand};
 
 #[cfg(feature = "today")]
 use advent_of_code::template::Day;
@@ -10,6 +10,32 @@ mod args {
     use advent_of_code::template::Day;
     use std::process;
 
+    pub enum IntcodeCommand {
+        Disasm {
+            file: String,
+        },
+        Cfg {
+            file: String,
+        },
+        Compile {
+            file: String,
+        },
+        Decompile {
+            file: String,
+        },
+        Debug {
+            file: String,
+            input: Option<String>,
+        },
+        Run {
+            file: String,
+            input: Option<String>,
+            ascii: bool,
+            max_steps: Option<u64>,
+            trace: Option<String>,
+        },
+    }
+
     pub enum AppArguments {
         Download {
             day: Day,
@@ -35,6 +61,7 @@ mod args {
             day: Option<Day>,
             store: bool,
         },
+        Intcode(IntcodeCommand),
         #[cfg(feature = "today")]
         Today,
     }
@@ -72,6 +99,7 @@ mod args {
                 submit: args.opt_value_from_str("--submit")?,
                 dhat: args.contains("--dhat"),
             },
+            Some("intcode") => AppArguments::Intcode(parse_intcode(&mut args)?),
             #[cfg(feature = "today")]
             Some("today") => AppArguments::Today,
             Some(x) => {
@@ -86,11 +114,53 @@ mod args {
 
         let remaining = args.finish();
         if !remaining.is_empty() {
+            if let AppArguments::Intcode(_) = app_args {
+                return Err(format!("unknown argument(s): {remaining:?}").into());
+            }
             eprintln!("Warning: unknown argument(s): {remaining:?}.");
         }
 
         Ok(app_args)
     }
+
+    fn parse_intcode(
+        args: &mut pico_args::Arguments,
+    ) -> Result<IntcodeCommand, Box<dyn std::error::Error>> {
+        let command = match args.subcommand()?.as_deref() {
+            Some("disasm") => IntcodeCommand::Disasm {
+                file: args.free_from_str()?,
+            },
+            Some("cfg") => IntcodeCommand::Cfg {
+                file: args.free_from_str()?,
+            },
+            Some("compile") => IntcodeCommand::Compile {
+                file: args.free_from_str()?,
+            },
+            Some("decompile") => IntcodeCommand::Decompile {
+                file: args.free_from_str()?,
+            },
+            Some("debug") => IntcodeCommand::Debug {
+                input: args.opt_value_from_str("--input")?,
+                file: args.free_from_str()?,
+            },
+            Some("run") => IntcodeCommand::Run {
+                input: args.opt_value_from_str("--input")?,
+                ascii: args.contains("--ascii"),
+                max_steps: args.opt_value_from_str("--max-steps")?,
+                trace: args.opt_value_from_str("--trace")?,
+                file: args.free_from_str()?,
+            },
+            Some(x) => {
+                eprintln!("Unknown intcode command: {x}");
+                process::exit(1);
+            }
+            None => {
+                eprintln!("No intcode command specified.");
+                process::exit(1);
+            }
+        };
+        Ok(command)
+    }
 }
 
 fn main() {
@@ -116,6 +186,24 @@ fn main() {
                 dhat,
                 submit,
             } => solve::handle(day, release, dhat, submit),
+            AppArguments::Intcode(command) => match command {
+                IntcodeCommand::Disasm { file } => intcode::handle_disasm(&file),
+                IntcodeCommand::Cfg { file } => intcode::handle_cfg(&file),
+                IntcodeCommand::Compile { file } => intcode::handle_compile(&file),
+                IntcodeCommand::Decompile { file } => intcode::handle_decompile(&file),
+                IntcodeCommand::Debug { file, input } => {
+                    intcode::handle_debug(&file, input.as_deref())
+                }
+                IntcodeCommand::Run {
+                    file,
+                    input,
+                    ascii,
+                    max_steps,
+                    trace,
+                } => {
+                    intcode::handle_run(&file, input.as_deref(), ascii, max_steps, trace.as_deref())
+                }
+            },
             #[cfg(feature = "today")]
             AppArguments::Today => {
                 match Day::today() {
@@ -0,0 +1,155 @@
+use crate::intcode_computer::{
+    analysis::Cfg,
+    ascii::AsciiCpu,
+    compile::compile,
+    cpu::Cpu,
+    debugger::Debugger,
+    decompile::decompile,
+    disasm,
+    error::CpuError,
+    io::{FnOutput, IterInput, StdinInput},
+    program::{ParseError, Program},
+    trace,
+};
+use std::{
+    fs::File,
+    io::{stdin, stdout, BufWriter, Write},
+    process,
+};
+
+fn read_program(path: &str) -> Vec<i64> {
+    Program::load(path)
+        .unwrap_or_else(|e| {
+            match e {
+                // already names the file
+                ParseError::Io(_) => eprintln!("could not load {e}"),
+                _ => eprintln!("could not load {path}: {e}"),
+            }
+            process::exit(1);
+        })
+        .into_vec()
+}
+
+fn parse_inputs(inputs: &str) -> Vec<i64> {
+    inputs
+        .split(',')
+        .map(|s| {
+            s.trim().parse().unwrap_or_else(|_| {
+                eprintln!("could not parse input {s:?} as an integer");
+                process::exit(1);
+            })
+        })
+        .collect()
+}
+
+pub fn handle_disasm(path: &str) {
+    let program = read_program(path);
+    print!("{}", disasm::listing(&program));
+}
+
+pub fn handle_cfg(path: &str) {
+    let program = read_program(path);
+    print!("{}", Cfg::build(&program).to_dot());
+}
+
+pub fn handle_compile(path: &str) {
+    let program = read_program(path);
+    print!("{}", compile(&program));
+}
+
+pub fn handle_decompile(path: &str) {
+    let program = read_program(path);
+    print!("{}", decompile(&program));
+}
+
+pub fn handle_debug(path: &str, inputs: Option<&str>) {
+    let mut cpu = Cpu::new(read_program(path));
+    for value in inputs.map(parse_inputs).unwrap_or_default() {
+        cpu.provide_input(value);
+    }
+
+    let mut debugger = Debugger::new(cpu);
+    if let Err(e) = debugger.repl(stdin().lock(), &mut stdout()) {
+        eprintln!("debugger failed: {e}");
+        process::exit(1);
+    }
+}
+
+/// Runs `cpu` until it halts, sending it the lines of `inputs`, or lines read from stdin when
+/// it waits for more.
+fn run_ascii(cpu: &mut AsciiCpu, inputs: Option<&str>) -> Result<(), CpuError> {
+    for line in inputs.iter().flat_map(|text| text.lines()) {
+        cpu.send_line(line);
+    }
+    loop {
+        let output = cpu.run()?;
+        print!("{}", output.text);
+        for value in output.non_ascii_values {
+            println!("{value}");
+        }
+        if cpu.is_halted() {
+            return Ok(());
+        }
+
+        let exhausted = CpuError::InputExhausted { pc: cpu.cpu.pc() };
+        if inputs.is_some() {
+            return Err(exhausted);
+        }
+        // show a prompt that does not end in a newline before blocking.
+        let _ = stdout().flush();
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => return Err(exhausted),
+            Ok(_) => cpu.send_line(line.strip_suffix('\n').unwrap_or(&line)),
+        }
+    }
+}
+
+/// Runs a program to completion, printing every output on its own line or, with `ascii`, as
+/// text. With `ascii`, `inputs` is text sent line by line instead of comma-separated integers.
+/// Without `inputs`, values are read from stdin.
+pub fn handle_run(
+    path: &str,
+    inputs: Option<&str>,
+    ascii: bool,
+    max_steps: Option<u64>,
+    trace_path: Option<&str>,
+) {
+    let mut cpu = Cpu::new(read_program(path));
+    if max_steps.is_some() {
+        cpu.max_steps = max_steps;
+    }
+    if trace_path.is_some() {
+        cpu.record_trace();
+    }
+
+    let (result, cpu) = if ascii {
+        let mut cpu = AsciiCpu::from(cpu);
+        (run_ascii(&mut cpu, inputs), cpu.cpu)
+    } else {
+        let output = FnOutput(|value: i64| println!("{value}"));
+        let result = match inputs {
+            Some(inputs) => cpu.run_with(IterInput(parse_inputs(inputs).into_iter()), output),
+            None => cpu.run_with(StdinInput, output),
+        };
+        (result, cpu)
+    };
+    let _ = stdout().flush();
+
+    if let Some(trace_path) = trace_path {
+        let written = File::create(trace_path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            trace::write_jsonl(cpu.trace.as_deref().unwrap_or_default(), &mut writer)?;
+            writer.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("could not write trace to {trace_path}: {e}");
+            process::exit(1);
+        }
+    }
+
+    if let Err(e) = result {
+        eprintln!("error: {e}");
+        process::exit(1);
+    }
+}
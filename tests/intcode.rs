@@ -0,0 +1,122 @@
+//! Runs `cargo intcode run` as a process to check its output and exit codes.
+use advent_of_code::intcode_computer::asm::assemble;
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+fn intcode(args: &[&str]) -> Output {
+    intcode_with_stdin(args, "")
+}
+
+fn intcode_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_advent_of_code"))
+        .arg("intcode")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Writes an assembled program to a file that is unique to this test.
+fn program_file(name: &str, source: &str) -> PathBuf {
+    let program = assemble(source).unwrap();
+    let text: Vec<_> = program.iter().map(i64::to_string).collect();
+    let path = std::env::temp_dir().join(format!("intcode-{}-{name}.txt", std::process::id()));
+    fs::write(&path, text.join(",")).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_run() {
+    let output = intcode(&["run", "data/examples/05-6.txt", "--input", "7"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "999\n");
+}
+
+#[test]
+fn test_unknown_argument() {
+    let output = intcode(&["run", "data/examples/09-1.txt", "--bogus"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).contains("unknown argument(s): [\"--bogus\"]"));
+}
+
+#[test]
+fn test_errors() {
+    let output = intcode(&["run", "data/examples/missing.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("could not load"));
+
+    let output = intcode(&["run", "data/examples/05-6.txt", "--input", "seven"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "could not parse input \"seven\" as an integer\n"
+    );
+}
+
+#[test]
+fn test_max_steps() {
+    let output = intcode(&["run", "data/examples/09-1.txt", "--max-steps", "5"]);
+    assert_eq!(output.status.code(), Some(1));
+    // outputs before the limit are still printed.
+    assert_eq!(stdout(&output), "109\n");
+    assert_eq!(stderr(&output), "error: step limit of 5 reached at pc 0\n");
+
+    let output = intcode(&["run", "data/examples/09-1.txt", "--max-steps", "1000"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().count(), 16);
+}
+
+#[test]
+fn test_ascii() {
+    let path = program_file(
+        "echo",
+        "loop:   in [c]
+                 out [c]
+                 eq [c], #10, [t]
+                 jf [t], #loop
+                 out #1000
+                 halt
+         c:      data 0
+         t:      data 0",
+    );
+    let path = path.to_str().unwrap();
+
+    let output = intcode(&["run", path, "--ascii", "--input", "hi"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // values outside of ASCII are printed as numbers.
+    assert_eq!(stdout(&output), "hi\n1000\n");
+
+    let output = intcode_with_stdin(&["run", path, "--ascii"], "yo\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "yo\n1000\n");
+
+    let output = intcode_with_stdin(&["run", path, "--ascii"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "error: input exhausted at pc 0\n");
+
+    let output = intcode(&["run", path, "--input", "104,10"]);
+    assert_eq!(stdout(&output), "104\n10\n1000\n");
+    fs::remove_file(path).unwrap();
+}